@@ -1,25 +1,203 @@
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 
 use crate::conversion::codec::{
-    add_audio_codec_args, add_fps_args, add_subtitle_codec_args, add_video_codec_args,
+    add_audio_codec_args, add_fps_args, add_subtitle_codec_args, add_two_pass_args,
+    add_video_codec_args,
 };
 use crate::conversion::error::ConversionError;
-use crate::conversion::filters::{build_audio_filters, build_video_filters};
+use crate::conversion::filters::{build_audio_filters, build_scale_filter, build_video_filters};
 use crate::conversion::media_rules::{
     container_supports_audio, container_supports_subtitles, is_audio_codec_allowed,
-    is_video_codec_allowed, is_video_only_container,
+    is_audio_stream_copy_allowed, is_video_codec_allowed, is_video_only_container,
+    is_video_stream_copy_allowed,
 };
-use crate::conversion::types::{ConversionConfig, MetadataConfig, MetadataMode};
-use crate::conversion::utils::{get_hwaccel_args, is_audio_only_container, parse_time};
+use crate::conversion::types::{
+    CollisionPolicy, ConversionConfig, MetadataConfig, MetadataMode, OutputSpec, ProbeMetadata,
+};
+use crate::conversion::upscale::resolve_upscale_mode;
+use crate::conversion::utils::{
+    NULL_OUTPUT, TEMPLATE_TOKEN_REGEX, get_hwaccel_args, is_audio_only_container,
+    is_bitrate_targeted, is_lossless_audio_codec, is_stream_copy, map_nvenc_preset_to_software,
+    nvenc_quality_to_crf, parse_time, software_encoder_for, supports_two_pass,
+    target_video_bitrate_kbps,
+};
+
+/// One pass of a two-pass encode. Pass 1 only analyses video and writes to the
+/// null muxer; pass 2 reads the stats from `log_prefix` and produces the output.
+#[derive(Debug, Clone, Copy)]
+pub struct EncodePass<'a> {
+    pub number: u8,
+    pub log_prefix: &'a str,
+}
+
+/// Target-size encodes always take the two-pass route when the encoder allows it,
+/// since a single pass tends to overshoot or undershoot the requested size.
+pub fn uses_two_pass(config: &ConversionConfig) -> bool {
+    (config.two_pass || config.video_bitrate_mode == "target_size")
+        && is_bitrate_targeted(&config.video_bitrate_mode)
+        && supports_two_pass(&config.video_codec)
+        && !is_audio_only_container(&config.container)
+        && !is_video_only_container(&config.container)
+}
+
+/// Length in seconds of the media a task produces: the cut-list total, the
+/// trim window, or the whole probed input.
+pub fn expected_output_duration(config: &ConversionConfig, probe: Option<&ProbeMetadata>) -> f64 {
+    if !config.segments.is_empty() {
+        return segment_bounds(config)
+            .iter()
+            .map(|(start, end)| (end - start).max(0.0))
+            .sum();
+    }
+
+    let start_t = config
+        .start_time
+        .as_deref()
+        .and_then(parse_time)
+        .unwrap_or(0.0);
+    let full_duration = probe
+        .and_then(|p| p.duration.as_deref())
+        .and_then(parse_time)
+        .unwrap_or(0.0);
+    let end_t = config
+        .end_time
+        .as_deref()
+        .and_then(parse_time)
+        .unwrap_or(full_duration);
+    (end_t - start_t).max(0.0)
+}
+
+/// Resolves the settings that depend on the probed source: the audio streams a
+/// cut-list graph has to name explicitly and the bitrate of a target-size encode.
+/// Shared by the app worker and `frame-cli` so both run identical pipelines.
+pub fn prepare_config(
+    config: &ConversionConfig,
+    probe: Option<&ProbeMetadata>,
+) -> Result<ConversionConfig, ConversionError> {
+    let mut prepared = config.clone();
+
+    if !prepared.segments.is_empty()
+        && !uses_concat_demuxer(&prepared)
+        && prepared.selected_audio_tracks.is_empty()
+    {
+        prepared.selected_audio_tracks = probe
+            .map(|p| p.audio_tracks.iter().map(|track| track.index).collect())
+            .unwrap_or_default();
+    }
+
+    if prepared.video_bitrate_mode == "target_size" {
+        let audio_streams = if prepared.selected_audio_tracks.is_empty() {
+            probe.map_or(0, |p| p.audio_tracks.len())
+        } else {
+            prepared.selected_audio_tracks.len()
+        };
+        let duration = expected_output_duration(&prepared, probe);
+        prepared = resolve_target_size(&prepared, duration, audio_streams)?;
+    }
+
+    Ok(prepared)
+}
+
+/// Config for retrying a failed NVENC encode in software when the task opted
+/// into `hw_fallback`. Constant-quality encodes map `quality` to the CRF that
+/// matches NVENC's `-cq`; bitrate-targeted encodes keep their bitrate.
+pub fn software_fallback_config(config: &ConversionConfig) -> Option<ConversionConfig> {
+    if !config.hw_fallback {
+        return None;
+    }
+    let software = software_encoder_for(&config.video_codec)?;
+
+    let mut fallback = config.clone();
+    fallback.video_codec = software.to_string();
+    fallback.crf = nvenc_quality_to_crf(config.quality, software);
+    fallback.preset = map_nvenc_preset_to_software(&config.preset, software);
+    fallback.nvenc_spatial_aq = false;
+    fallback.nvenc_temporal_aq = false;
+    Some(fallback)
+}
+
+/// Fills in `video_bitrate` for a `"target_size"` encode from the length of the
+/// trimmed output and the bitrate taken up by `audio_streams` audio streams.
+pub fn resolve_target_size(
+    config: &ConversionConfig,
+    duration: f64,
+    audio_streams: usize,
+) -> Result<ConversionConfig, ConversionError> {
+    let target_mb = config.target_size_mb.unwrap_or(0.0);
+    if duration <= 0.0 {
+        return Err(ConversionError::InvalidInput(
+            "Target size mode needs a known input duration".to_string(),
+        ));
+    }
+
+    let carries_audio = container_supports_audio(&config.container)
+        && !is_lossless_audio_codec(&config.audio_codec);
+    let audio_kbps = if carries_audio {
+        config.audio_bitrate.parse::<f64>().unwrap_or(0.0) * audio_streams as f64
+    } else {
+        0.0
+    };
+
+    let video_kbps =
+        target_video_bitrate_kbps(target_mb, duration, audio_kbps).ok_or_else(|| {
+            ConversionError::InvalidInput(format!(
+                "Target size of {} MB is too small for {:.1}s of output",
+                target_mb, duration
+            ))
+        })?;
+
+    let mut resolved = config.clone();
+    resolved.video_bitrate = format!("{}", video_kbps as u64);
+    Ok(resolved)
+}
+
+pub fn build_two_pass_args(
+    input: &str,
+    output: &str,
+    config: &ConversionConfig,
+    log_prefix: &str,
+) -> [Vec<String>; 2] {
+    [1, 2].map(|number| {
+        build_ffmpeg_pass_args(
+            input,
+            output,
+            config,
+            Some(EncodePass { number, log_prefix }),
+        )
+    })
+}
 
 pub fn build_ffmpeg_args(input: &str, output: &str, config: &ConversionConfig) -> Vec<String> {
+    build_ffmpeg_pass_args(input, output, config, None)
+}
+
+fn build_ffmpeg_pass_args(
+    input: &str,
+    output: &str,
+    config: &ConversionConfig,
+    pass: Option<EncodePass>,
+) -> Vec<String> {
     let mut args = Vec::new();
+    let is_analysis_pass = pass.is_some_and(|p| p.number == 1);
+    add_filter_thread_args(&mut args, config);
+
+    let copies_video = is_stream_copy(&config.video_codec);
+    let segment_graph = !config.segments.is_empty() && !uses_concat_demuxer(config);
 
     // Hardware decode acceleration (must be before -i)
-    if config.hw_decode {
+    if config.hw_decode && !copies_video {
         args.extend(get_hwaccel_args(&config.video_codec));
     }
 
+    if uses_concat_demuxer(config) {
+        // `input` is the list written from `build_concat_list`; it carries the cut points.
+        args.push("-f".to_string());
+        args.push("concat".to_string());
+        args.push("-safe".to_string());
+        args.push("0".to_string());
+    }
+
     if let Some(start) = &config.start_time {
         if !start.is_empty() {
             args.push("-ss".to_string());
@@ -29,45 +207,8 @@ pub fn build_ffmpeg_args(input: &str, output: &str, config: &ConversionConfig) -
 
     args.push("-i".to_string());
     args.push(input.to_string());
-
-    if let Some(end_str) = &config.end_time {
-        if !end_str.is_empty() {
-            if let Some(start_str) = &config.start_time {
-                if !start_str.is_empty() {
-                    if let (Some(start_t), Some(end_t)) =
-                        (parse_time(start_str), parse_time(end_str))
-                    {
-                        let duration = end_t - start_t;
-                        if duration > 0.0 {
-                            args.push("-t".to_string());
-                            args.push(format!("{:.3}", duration));
-                        }
-                    }
-                } else {
-                    args.push("-to".to_string());
-                    args.push(end_str.clone());
-                }
-            } else {
-                args.push("-to".to_string());
-                args.push(end_str.clone());
-            }
-        }
-    }
-
-    match config.metadata.mode {
-        MetadataMode::Clean => {
-            args.push("-map_metadata".to_string());
-            args.push("-1".to_string());
-        }
-        MetadataMode::Replace => {
-            args.push("-map_metadata".to_string());
-            args.push("-1".to_string());
-            add_metadata_flags(&mut args, &config.metadata);
-        }
-        MetadataMode::Preserve => {
-            add_metadata_flags(&mut args, &config.metadata);
-        }
-    }
+    add_end_time_args(&mut args, config);
+    add_metadata_args(&mut args, config);
 
     let is_audio_only = is_audio_only_container(&config.container);
     let is_video_only = is_video_only_container(&config.container);
@@ -79,7 +220,11 @@ pub fn build_ffmpeg_args(input: &str, output: &str, config: &ConversionConfig) -
     if is_audio_only {
         args.push("-vn".to_string());
 
-        if !config.selected_audio_tracks.is_empty() {
+        if segment_graph {
+            args.push("-filter_complex".to_string());
+            args.push(build_segment_filter_complex(config, false, true));
+            add_segment_audio_maps(&mut args, config);
+        } else if !config.selected_audio_tracks.is_empty() {
             for track_index in &config.selected_audio_tracks {
                 args.push("-map".to_string());
                 args.push(format!("0:{}", track_index));
@@ -107,43 +252,53 @@ pub fn build_ffmpeg_args(input: &str, output: &str, config: &ConversionConfig) -
         args.push("gif".to_string());
     } else {
         add_video_codec_args(&mut args, config);
-
-        let video_filters = build_video_filters(config, true);
-        if !video_filters.is_empty() {
-            args.push("-vf".to_string());
-            args.push(video_filters.join(","));
+        if let Some(pass) = pass {
+            add_two_pass_args(&mut args, config, pass.number, pass.log_prefix);
         }
 
-        add_fps_args(&mut args, config);
-        args.push("-map".to_string());
-        args.push("0:v:0".to_string());
+        if !copies_video {
+            let video_filters = build_video_filters(config, true);
+            if !video_filters.is_empty() && !segment_graph {
+                args.push("-vf".to_string());
+                args.push(video_filters.join(","));
+            }
 
-        if !config.selected_audio_tracks.is_empty() {
-            for track_index in &config.selected_audio_tracks {
-                args.push("-map".to_string());
-                args.push(format!("0:{}", track_index));
+            add_fps_args(&mut args, config);
+        }
+
+        if segment_graph {
+            // Soft subtitles cannot pass through the trim graph and would drift; they are
+            // rejected during validation, and burned-in ones are applied before the cuts.
+            args.push("-filter_complex".to_string());
+            args.push(build_segment_filter_complex(
+                config,
+                true,
+                !is_analysis_pass,
+            ));
+            args.push("-map".to_string());
+            args.push("[vout]".to_string());
+            if is_analysis_pass {
+                args.push("-an".to_string());
+            } else {
+                add_segment_audio_maps(&mut args, config);
+                add_audio_codec_args(&mut args, config);
             }
+            args.push("-sn".to_string());
         } else {
             args.push("-map".to_string());
-            args.push("0:a?".to_string());
-        }
-
-        add_audio_codec_args(&mut args, config);
+            args.push("0:v:0".to_string());
 
-        if !config.selected_subtitle_tracks.is_empty() {
-            for track_index in &config.selected_subtitle_tracks {
-                args.push("-map".to_string());
-                args.push(format!("0:{}", track_index));
+            if is_analysis_pass {
+                args.push("-an".to_string());
+                args.push("-sn".to_string());
+            } else {
+                add_audio_and_subtitle_streams(&mut args, config, has_burn_subtitles);
             }
-            add_subtitle_codec_args(&mut args, config);
-        } else if !has_burn_subtitles {
-            args.push("-map".to_string());
-            args.push("0:s?".to_string());
-            add_subtitle_codec_args(&mut args, config);
         }
     }
 
-    if !is_video_only {
+    if !is_video_only && !is_analysis_pass && !segment_graph && !is_stream_copy(&config.audio_codec)
+    {
         let audio_filters = build_audio_filters(config);
         if !audio_filters.is_empty() {
             args.push("-af".to_string());
@@ -151,12 +306,243 @@ pub fn build_ffmpeg_args(input: &str, output: &str, config: &ConversionConfig) -
         }
     }
 
+    if uses_keyframe_trim(config) {
+        // Copied packets keep their source timestamps; shift them so the cut starts at zero.
+        args.push("-avoid_negative_ts".to_string());
+        args.push("make_zero".to_string());
+    }
+
+    add_encoder_thread_args(&mut args, config);
     args.push("-y".to_string());
-    args.push(output.to_string());
+    if is_analysis_pass {
+        args.push("-f".to_string());
+        args.push("null".to_string());
+        args.push(NULL_OUTPUT.to_string());
+    } else {
+        args.push(output.to_string());
+    }
 
     args
 }
 
+fn add_end_time_args(args: &mut Vec<String>, config: &ConversionConfig) {
+    if let Some(end_str) = &config.end_time {
+        if !end_str.is_empty() {
+            if let Some(start_str) = &config.start_time {
+                if !start_str.is_empty() {
+                    if let (Some(start_t), Some(end_t)) =
+                        (parse_time(start_str), parse_time(end_str))
+                    {
+                        let duration = end_t - start_t;
+                        if duration > 0.0 {
+                            args.push("-t".to_string());
+                            args.push(format!("{:.3}", duration));
+                        }
+                    }
+                } else {
+                    args.push("-to".to_string());
+                    args.push(end_str.clone());
+                }
+            } else {
+                args.push("-to".to_string());
+                args.push(end_str.clone());
+            }
+        }
+    }
+}
+
+fn add_metadata_args(args: &mut Vec<String>, config: &ConversionConfig) {
+    match config.metadata.mode {
+        MetadataMode::Clean => {
+            args.push("-map_metadata".to_string());
+            args.push("-1".to_string());
+        }
+        MetadataMode::Replace => {
+            args.push("-map_metadata".to_string());
+            args.push("-1".to_string());
+            add_metadata_flags(args, &config.metadata);
+        }
+        MetadataMode::Preserve => {
+            add_metadata_flags(args, &config.metadata);
+        }
+    }
+}
+
+/// Config for one rendition of a multi-output task: the main config with the
+/// spec's fields applied on top.
+pub fn output_variant_config(config: &ConversionConfig, spec: &OutputSpec) -> ConversionConfig {
+    let mut variant = config.clone();
+    variant.additional_outputs = Vec::new();
+    variant.container = spec.container.clone();
+    variant.video_codec = spec.video_codec.clone();
+    variant.resolution = spec.resolution.clone();
+    if let Some(mode) = &spec.video_bitrate_mode {
+        variant.video_bitrate_mode = mode.clone();
+    }
+    if let Some(bitrate) = &spec.video_bitrate {
+        variant.video_bitrate = bitrate.clone();
+    }
+    if let Some(crf) = spec.crf {
+        variant.crf = crf;
+    }
+    if let Some(preset) = &spec.preset {
+        variant.preset = preset.clone();
+    }
+    if let Some(codec) = &spec.audio_codec {
+        variant.audio_codec = codec.clone();
+    }
+    if let Some(bitrate) = &spec.audio_bitrate {
+        variant.audio_bitrate = bitrate.clone();
+    }
+    variant
+}
+
+/// Where a rendition is written: next to the main output, named after it with
+/// the resolution appended unless the spec names the file itself.
+pub fn additional_output_path(
+    file_path: &str,
+    main_output: &str,
+    spec: &OutputSpec,
+    variant: &ConversionConfig,
+) -> String {
+    let name = spec.output_name.clone().unwrap_or_else(|| {
+        let stem = Path::new(main_output)
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        format!("{}_{}", stem, spec.resolution)
+    });
+    let output = build_output_path(file_path, &variant.container, Some(name));
+    match Path::new(main_output).parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir
+            .join(Path::new(&output).file_name().unwrap_or_default())
+            .to_string_lossy()
+            .to_string(),
+        _ => output,
+    }
+}
+
+/// One ffmpeg command that decodes `input` once and encodes every entry of
+/// `outputs`. The shared filters run before a `split`; each branch is then
+/// scaled for its own output. Decoding stays in software since every branch
+/// has to be filtered on the CPU.
+pub fn build_multi_output_args(input: &str, outputs: &[(String, ConversionConfig)]) -> Vec<String> {
+    let mut args = Vec::new();
+    let Some((_, main)) = outputs.first() else {
+        return args;
+    };
+    add_filter_thread_args(&mut args, main);
+
+    if let Some(start) = main.start_time.as_deref().filter(|s| !s.is_empty()) {
+        args.push("-ss".to_string());
+        args.push(start.to_string());
+    }
+    args.push("-i".to_string());
+    args.push(input.to_string());
+    add_end_time_args(&mut args, main);
+
+    let mut graph = String::from("[0:v:0]");
+    let shared = build_video_filters(main, false);
+    if !shared.is_empty() {
+        graph.push_str(&shared.join(","));
+        graph.push(',');
+    }
+    graph.push_str(&format!("split={}", outputs.len()));
+    for i in 0..outputs.len() {
+        graph.push_str(&format!("[s{}]", i));
+    }
+    for (i, (_, config)) in outputs.iter().enumerate() {
+        let scale = build_scale_filter(config).unwrap_or_else(|| "null".to_string());
+        graph.push_str(&format!(";[s{}]{}[v{}]", i, scale, i));
+    }
+    args.push("-filter_complex".to_string());
+    args.push(graph);
+    args.push("-y".to_string());
+
+    let has_burn_subtitles = main
+        .subtitle_burn_path
+        .as_ref()
+        .is_some_and(|path| !path.trim().is_empty());
+    for (i, (output, config)) in outputs.iter().enumerate() {
+        args.push("-map".to_string());
+        args.push(format!("[v{}]", i));
+        add_video_codec_args(&mut args, config);
+        add_fps_args(&mut args, config);
+        add_audio_and_subtitle_streams(&mut args, config, has_burn_subtitles);
+
+        if !is_stream_copy(&config.audio_codec) {
+            let audio_filters = build_audio_filters(config);
+            if !audio_filters.is_empty() {
+                args.push("-af".to_string());
+                args.push(audio_filters.join(","));
+            }
+        }
+
+        add_metadata_args(&mut args, config);
+        add_encoder_thread_args(&mut args, config);
+        args.push(output.clone());
+    }
+
+    args
+}
+
+/// Multi-output tasks share one decode and filter graph, so every rendition
+/// has to be a plain single-pass encode of the same video stream.
+fn validate_additional_outputs(
+    file_path: &str,
+    config: &ConversionConfig,
+    probe: Option<&ProbeMetadata>,
+) -> Result<(), ConversionError> {
+    let unsupported = |feature: &str| {
+        Err(ConversionError::InvalidInput(format!(
+            "{} is not available together with additional outputs",
+            feature
+        )))
+    };
+
+    if config
+        .ml_upscale
+        .as_ref()
+        .is_some_and(|mode| !mode.is_empty() && mode != "none")
+    {
+        return unsupported("ML upscaling");
+    }
+    if !config.segments.is_empty() {
+        return unsupported("A cut list");
+    }
+    if config.hw_fallback {
+        return unsupported("Hardware encoder fallback");
+    }
+
+    let check_output = |output: &ConversionConfig| {
+        if is_audio_only_container(&output.container) || is_video_only_container(&output.container)
+        {
+            return Err(ConversionError::InvalidInput(format!(
+                "Container '{}' cannot be written as one of several outputs",
+                output.container
+            )));
+        }
+        if is_stream_copy(&output.video_codec) {
+            return unsupported("Video stream copy");
+        }
+        if output.two_pass || output.video_bitrate_mode == "target_size" {
+            return unsupported("Two-pass and target-size encoding");
+        }
+        Ok(())
+    };
+
+    // The main output is validated by the caller; each rendition gets the
+    // same checks on its own config.
+    check_output(config)?;
+    for spec in &config.additional_outputs {
+        let variant = output_variant_config(config, spec);
+        check_output(&variant)?;
+        validate_task_input(file_path, &variant, probe)?;
+    }
+
+    Ok(())
+}
+
 fn normalize_gif_dither(dither: &str) -> &'static str {
     match dither {
         "none" => "none",
@@ -187,6 +573,303 @@ fn build_gif_filter_complex(config: &ConversionConfig) -> String {
     )
 }
 
+/// Parsed `(start, end)` seconds of each cut-list segment, in output order.
+pub fn segment_bounds(config: &ConversionConfig) -> Vec<(f64, f64)> {
+    config
+        .segments
+        .iter()
+        .filter_map(|segment| Some((parse_time(&segment.start)?, parse_time(&segment.end)?)))
+        .collect()
+}
+
+/// Stream-copied cut lists are joined with the concat demuxer, since packets
+/// cannot go through the `trim`/`concat` filter graph used for re-encodes.
+pub fn uses_concat_demuxer(config: &ConversionConfig) -> bool {
+    let copies_primary_stream = if is_audio_only_container(&config.container) {
+        copies_audio(config)
+    } else {
+        copies_video(config)
+    };
+    !config.segments.is_empty() && copies_primary_stream
+}
+
+/// ffconcat script that reads each segment of `input` in turn. Every entry
+/// points at the same file, so stream layout and timestamps line up across joins.
+pub fn build_concat_list(input: &str, segments: &[(f64, f64)]) -> String {
+    let escaped_input = input.replace('\\', "/").replace('\'', "'\\''");
+    let mut list = String::from("ffconcat version 1.0\n");
+    for (start, end) in segments {
+        list.push_str(&format!(
+            "file '{}'\ninpoint {:.6}\noutpoint {:.6}\n",
+            escaped_input, start, end
+        ));
+    }
+    list
+}
+
+/// Splits the source into one branch per segment, trims each branch and joins
+/// them with `concat`. Video filters run before the split so burned-in
+/// subtitles keep source timing; audio filters run on the joined result.
+fn build_segment_filter_complex(
+    config: &ConversionConfig,
+    include_video: bool,
+    include_audio: bool,
+) -> String {
+    let segments = segment_bounds(config);
+    let count = segments.len();
+    let audio_tracks: &[u32] = if include_audio {
+        &config.selected_audio_tracks
+    } else {
+        &[]
+    };
+    let mut chains = Vec::new();
+
+    if include_video {
+        let mut filters = build_video_filters(config, true);
+        filters.push(format!(
+            "split={}{}",
+            count,
+            (0..count).map(|i| format!("[vs{}]", i)).collect::<String>()
+        ));
+        chains.push(format!("[0:v:0]{}", filters.join(",")));
+        for (i, (start, end)) in segments.iter().enumerate() {
+            chains.push(format!(
+                "[vs{i}]trim=start={start:.3}:end={end:.3},setpts=PTS-STARTPTS[v{i}]"
+            ));
+        }
+    }
+
+    for (t, track_index) in audio_tracks.iter().enumerate() {
+        chains.push(format!(
+            "[0:{}]asplit={}{}",
+            track_index,
+            count,
+            (0..count)
+                .map(|i| format!("[as{}_{}]", t, i))
+                .collect::<String>()
+        ));
+        for (i, (start, end)) in segments.iter().enumerate() {
+            chains.push(format!(
+                "[as{t}_{i}]atrim=start={start:.3}:end={end:.3},asetpts=PTS-STARTPTS[a{t}_{i}]"
+            ));
+        }
+    }
+
+    let audio_filters = build_audio_filters(config);
+    let audio_label = if audio_filters.is_empty() {
+        "aout"
+    } else {
+        "acat"
+    };
+
+    let mut concat_inputs = String::new();
+    for i in 0..count {
+        if include_video {
+            concat_inputs.push_str(&format!("[v{}]", i));
+        }
+        for t in 0..audio_tracks.len() {
+            concat_inputs.push_str(&format!("[a{}_{}]", t, i));
+        }
+    }
+    let mut concat_outputs = String::new();
+    if include_video {
+        concat_outputs.push_str("[vout]");
+    }
+    for t in 0..audio_tracks.len() {
+        concat_outputs.push_str(&format!("[{}{}]", audio_label, t));
+    }
+    chains.push(format!(
+        "{}concat=n={}:v={}:a={}{}",
+        concat_inputs,
+        count,
+        u8::from(include_video),
+        audio_tracks.len(),
+        concat_outputs
+    ));
+
+    if !audio_filters.is_empty() {
+        for t in 0..audio_tracks.len() {
+            chains.push(format!("[acat{t}]{}[aout{t}]", audio_filters.join(",")));
+        }
+    }
+
+    chains.join(";")
+}
+
+fn add_segment_audio_maps(args: &mut Vec<String>, config: &ConversionConfig) {
+    for t in 0..config.selected_audio_tracks.len() {
+        args.push("-map".to_string());
+        args.push(format!("[aout{}]", t));
+    }
+}
+
+fn add_audio_and_subtitle_streams(
+    args: &mut Vec<String>,
+    config: &ConversionConfig,
+    has_burn_subtitles: bool,
+) {
+    if !config.selected_audio_tracks.is_empty() {
+        for track_index in &config.selected_audio_tracks {
+            args.push("-map".to_string());
+            args.push(format!("0:{}", track_index));
+        }
+    } else {
+        args.push("-map".to_string());
+        args.push("0:a?".to_string());
+    }
+
+    add_audio_codec_args(args, config);
+
+    if !config.selected_subtitle_tracks.is_empty() {
+        for track_index in &config.selected_subtitle_tracks {
+            args.push("-map".to_string());
+            args.push(format!("0:{}", track_index));
+        }
+        add_subtitle_codec_args(args, config);
+    } else if !has_burn_subtitles {
+        args.push("-map".to_string());
+        args.push("0:s?".to_string());
+        add_subtitle_codec_args(args, config);
+    }
+}
+
+fn validate_segments(
+    config: &ConversionConfig,
+    has_ml_upscale: bool,
+) -> Result<(), ConversionError> {
+    let has_trim = [&config.start_time, &config.end_time]
+        .iter()
+        .any(|t| t.as_deref().is_some_and(|t| !t.trim().is_empty()));
+    if has_trim {
+        return Err(ConversionError::InvalidInput(
+            "Use either a cut list or a start/end trim, not both".to_string(),
+        ));
+    }
+
+    for segment in &config.segments {
+        let (Some(start), Some(end)) = (parse_time(&segment.start), parse_time(&segment.end))
+        else {
+            return Err(ConversionError::InvalidInput(format!(
+                "Invalid segment: {} - {}",
+                segment.start, segment.end
+            )));
+        };
+        if end <= start {
+            return Err(ConversionError::InvalidInput(format!(
+                "Segment end must be greater than its start: {} - {}",
+                segment.start, segment.end
+            )));
+        }
+    }
+
+    if is_video_only_container(&config.container) || has_ml_upscale {
+        return Err(ConversionError::InvalidInput(
+            "Cut lists are not available for GIF output or ML upscaling".to_string(),
+        ));
+    }
+
+    if !uses_concat_demuxer(config) {
+        if copies_audio(config) {
+            return Err(ConversionError::InvalidInput(
+                "Cut lists can only copy audio when the video is copied too".to_string(),
+            ));
+        }
+        if !config.selected_subtitle_tracks.is_empty() {
+            return Err(ConversionError::InvalidInput(
+                "Subtitle tracks can only be kept across cuts in stream copy mode; burn them in instead"
+                    .to_string(),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_stream_copy(
+    config: &ConversionConfig,
+    probe: Option<&ProbeMetadata>,
+    has_ml_upscale: bool,
+) -> Result<(), ConversionError> {
+    let probe = probe.ok_or_else(|| {
+        ConversionError::InvalidInput("Stream copy requires source stream information".to_string())
+    })?;
+
+    if copies_video(config) {
+        if is_video_only_container(&config.container) {
+            return Err(ConversionError::InvalidInput(format!(
+                "Stream copy is not available for container '{}'",
+                config.container
+            )));
+        }
+        if !build_video_filters(config, true).is_empty() || config.fps != "original" {
+            return Err(ConversionError::InvalidInput(
+                "Video filters, scaling and frame rate changes require re-encoding the video"
+                    .to_string(),
+            ));
+        }
+        if has_ml_upscale || config.video_bitrate_mode == "target_size" {
+            return Err(ConversionError::InvalidInput(
+                "ML upscaling and target size encoding require re-encoding the video".to_string(),
+            ));
+        }
+
+        let source_codec = probe.video_codec.as_deref().ok_or_else(|| {
+            ConversionError::InvalidInput("Source has no video stream to copy".to_string())
+        })?;
+        if !is_video_stream_copy_allowed(&config.container, source_codec) {
+            return Err(ConversionError::InvalidInput(format!(
+                "Source video codec '{}' cannot be copied into container '{}'",
+                source_codec, config.container
+            )));
+        }
+    }
+
+    if copies_audio(config) {
+        if !build_audio_filters(config).is_empty()
+            || matches!(config.audio_channels.as_str(), "stereo" | "mono")
+        {
+            return Err(ConversionError::InvalidInput(
+                "Volume, normalization and channel changes require re-encoding the audio"
+                    .to_string(),
+            ));
+        }
+
+        let copied_tracks = probe.audio_tracks.iter().filter(|track| {
+            config.selected_audio_tracks.is_empty()
+                || config.selected_audio_tracks.contains(&track.index)
+        });
+        for track in copied_tracks {
+            if !is_audio_stream_copy_allowed(&config.container, &track.codec) {
+                return Err(ConversionError::InvalidInput(format!(
+                    "Source audio codec '{}' cannot be copied into container '{}'",
+                    track.codec, config.container
+                )));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Global options capping the threads of `-vf`/`-af` chains and of
+/// `-filter_complex` graphs; they go first so every graph in the command obeys them.
+pub fn add_filter_thread_args(args: &mut Vec<String>, config: &ConversionConfig) {
+    if let Some(threads) = config.threads.filter(|t| *t > 0) {
+        args.push("-filter_threads".to_string());
+        args.push(threads.to_string());
+        args.push("-filter_complex_threads".to_string());
+        args.push(threads.to_string());
+    }
+}
+
+/// Output option capping the encoder threads of the output that follows.
+pub fn add_encoder_thread_args(args: &mut Vec<String>, config: &ConversionConfig) {
+    if let Some(threads) = config.threads.filter(|t| *t > 0) {
+        args.push("-threads".to_string());
+        args.push(threads.to_string());
+    }
+}
+
 pub fn add_metadata_flags(args: &mut Vec<String>, metadata: &MetadataConfig) {
     if let Some(v) = &metadata.title {
         if !v.is_empty() {
@@ -260,9 +943,270 @@ pub fn build_output_path(file_path: &str, container: &str, output_name: Option<S
     }
 }
 
+/// Like `build_output_path`, but honours the config's destination directory.
+pub fn resolve_output_path(
+    file_path: &str,
+    config: &ConversionConfig,
+    output_name: Option<String>,
+) -> String {
+    let output = build_output_path(file_path, &config.container, output_name);
+    match output_directory(config) {
+        Some(dir) => Path::new(dir)
+            .join(Path::new(&output).file_name().unwrap_or_default())
+            .to_string_lossy()
+            .to_string(),
+        None => output,
+    }
+}
+
+fn output_directory(config: &ConversionConfig) -> Option<&str> {
+    config
+        .output_directory
+        .as_deref()
+        .map(str::trim)
+        .filter(|dir| !dir.is_empty())
+}
+
+pub fn output_template(config: &ConversionConfig) -> Option<&str> {
+    config
+        .output_template
+        .as_deref()
+        .map(str::trim)
+        .filter(|template| !template.is_empty())
+}
+
+const OUTPUT_TEMPLATE_TOKENS: [&str; 7] = [
+    "name",
+    "ext",
+    "container",
+    "codec",
+    "height",
+    "date",
+    "preset",
+];
+
+fn validate_output_template(template: &str) -> Result<(), ConversionError> {
+    match TEMPLATE_TOKEN_REGEX
+        .captures_iter(template)
+        .filter_map(|caps| caps.get(1))
+        .find(|token| !OUTPUT_TEMPLATE_TOKENS.contains(&token.as_str()))
+    {
+        Some(token) => Err(ConversionError::InvalidInput(format!(
+            "Unknown output template token: {{{}}}",
+            token.as_str()
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Token values may come from file names or probe tags, so anything that could
+/// act as a path separator or is invalid on Windows is replaced.
+fn sanitize_template_value(value: &str) -> String {
+    value
+        .trim()
+        .chars()
+        .map(|ch| match ch {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            ch if ch.is_control() => '_',
+            ch => ch,
+        })
+        .collect()
+}
+
+/// Height of the encoded video: the requested scale target, otherwise the
+/// probed source height adjusted for rotation and ML upscaling.
+pub(crate) fn output_height(
+    config: &ConversionConfig,
+    probe: Option<&ProbeMetadata>,
+) -> Option<u32> {
+    let source = probe.and_then(|p| match config.rotation.as_str() {
+        "90" | "270" => p.width.zip(p.height).map(|(w, h)| (h, w)),
+        _ => p.width.zip(p.height),
+    });
+
+    let scaled = match config.resolution.as_str() {
+        "1080p" => Some(1080),
+        "720p" => Some(720),
+        "480p" => Some(480),
+        "custom" => {
+            let parse = |value: &Option<String>| {
+                value
+                    .as_deref()
+                    .and_then(|v| v.trim().parse::<u32>().ok())
+                    .filter(|v| *v > 0)
+            };
+            match (parse(&config.custom_width), parse(&config.custom_height)) {
+                (_, Some(height)) => Some(height),
+                (Some(width), None) => source.filter(|(w, _)| *w > 0).map(|(w, h)| {
+                    ((h as f64 * width as f64 / w as f64 / 2.0).round() * 2.0) as u32
+                }),
+                (None, None) => None,
+            }
+        }
+        _ => None,
+    };
+
+    let upscale = config
+        .ml_upscale
+        .as_deref()
+        .and_then(|mode| resolve_upscale_mode(mode).ok())
+        .and_then(|(scale, _)| scale.parse::<u32>().ok())
+        .unwrap_or(1);
+
+    scaled.or_else(|| source.map(|(_, h)| h * upscale))
+}
+
+/// Expands an output file name template into an output name for
+/// `build_output_path`, which applies the same sanitization as to a hand-typed
+/// name. The container extension is appended so dots inside `{name}` survive.
+pub fn render_output_template(
+    template: &str,
+    file_path: &str,
+    config: &ConversionConfig,
+    probe: Option<&ProbeMetadata>,
+    preset_name: Option<&str>,
+    date: &str,
+) -> Result<String, ConversionError> {
+    validate_output_template(template)?;
+
+    let input_path = Path::new(file_path);
+    let audio_only = is_audio_only_container(&config.container);
+    let codec = if audio_only {
+        &config.audio_codec
+    } else {
+        &config.video_codec
+    };
+    let codec = if is_stream_copy(codec) {
+        probe
+            .and_then(|p| {
+                if audio_only {
+                    p.audio_codec.clone()
+                } else {
+                    p.video_codec.clone()
+                }
+            })
+            .unwrap_or_else(|| codec.clone())
+    } else {
+        codec.clone()
+    };
+
+    let mut missing = None;
+    let rendered = TEMPLATE_TOKEN_REGEX.replace_all(template, |caps: &regex::Captures| {
+        let token = &caps[1];
+        let value = match token {
+            "name" => input_path
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string()),
+            "ext" => input_path
+                .extension()
+                .map(|s| s.to_string_lossy().to_string()),
+            "container" => Some(config.container.clone()),
+            "codec" => Some(codec.clone()),
+            "height" => output_height(config, probe).map(|h| h.to_string()),
+            "date" => Some(date.to_string()),
+            "preset" => Some(preset_name.unwrap_or("custom").to_string()),
+            _ => None,
+        };
+        value
+            .map(|v| sanitize_template_value(&v))
+            .unwrap_or_else(|| {
+                missing.get_or_insert_with(|| token.to_string());
+                String::new()
+            })
+    });
+
+    if let Some(token) = missing {
+        return Err(ConversionError::InvalidInput(format!(
+            "Output template token {{{}}} could not be resolved for {}",
+            token, file_path
+        )));
+    }
+
+    let rendered = rendered.trim().to_string();
+    if sanitize_output_name(&rendered).is_none() {
+        return Err(ConversionError::InvalidInput(format!(
+            "Output template produced an empty file name: {}",
+            template
+        )));
+    }
+    Ok(format!("{}.{}", rendered, config.container))
+}
+
+/// Applies a collision policy to `output_path`. `reserved` holds the outputs
+/// of tasks that are already running, which are never handed out twice, and
+/// `exists` reports files already on disk. `Ok(None)` means skip the task.
+pub fn resolve_output_collision(
+    output_path: &str,
+    policy: CollisionPolicy,
+    reserved: &HashSet<String>,
+    exists: impl Fn(&str) -> bool,
+) -> Result<Option<String>, ConversionError> {
+    let taken = |path: &str| reserved.contains(path) || exists(path);
+
+    match policy {
+        CollisionPolicy::Overwrite if reserved.contains(output_path) => {
+            Err(ConversionError::OutputExists(format!(
+                "{} is being written by another task",
+                output_path
+            )))
+        }
+        CollisionPolicy::Overwrite => Ok(Some(output_path.to_string())),
+        CollisionPolicy::Skip if taken(output_path) => Ok(None),
+        CollisionPolicy::Fail if taken(output_path) => {
+            Err(ConversionError::OutputExists(output_path.to_string()))
+        }
+        CollisionPolicy::Skip | CollisionPolicy::Fail => Ok(Some(output_path.to_string())),
+        CollisionPolicy::AutoIncrement => {
+            let path = Path::new(output_path);
+            let stem = path
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let extension = path.extension().map(|e| e.to_string_lossy().to_string());
+
+            let candidate = std::iter::once(output_path.to_string())
+                .chain((1..).map(|n| {
+                    let name = match &extension {
+                        Some(ext) => format!("{} ({}).{}", stem, n, ext),
+                        None => format!("{} ({})", stem, n),
+                    };
+                    path.with_file_name(name).to_string_lossy().to_string()
+                }))
+                .find(|candidate| !taken(candidate));
+            Ok(candidate)
+        }
+    }
+}
+
+fn copies_video(config: &ConversionConfig) -> bool {
+    !is_audio_only_container(&config.container) && is_stream_copy(&config.video_codec)
+}
+
+fn copies_audio(config: &ConversionConfig) -> bool {
+    container_supports_audio(&config.container) && is_stream_copy(&config.audio_codec)
+}
+
+/// Stream-copied video can only start on a keyframe, so copy-mode trims and
+/// cut-list segments are snapped to one before the task runs instead of
+/// re-encoding the window.
+pub fn uses_keyframe_trim(config: &ConversionConfig) -> bool {
+    let has_start = config
+        .start_time
+        .as_deref()
+        .is_some_and(|start| !start.trim().is_empty());
+    copies_video(config) && (has_start || !config.segments.is_empty())
+}
+
+pub fn uses_stream_copy(config: &ConversionConfig) -> bool {
+    copies_video(config) || copies_audio(config)
+}
+
+/// `probe` is required when the config stream-copies any stream, since copy
+/// compatibility depends on the source codecs rather than the selected encoder.
 pub fn validate_task_input(
     file_path: &str,
     config: &ConversionConfig,
+    probe: Option<&ProbeMetadata>,
 ) -> Result<(), ConversionError> {
     let input_path = Path::new(file_path);
     if !input_path.exists() {
@@ -278,6 +1222,16 @@ pub fn validate_task_input(
         )));
     }
 
+    if let Some(template) = output_template(config) {
+        validate_output_template(template)?;
+    }
+    if let Some(dir) = output_directory(config).filter(|dir| !Path::new(dir).is_dir()) {
+        return Err(ConversionError::InvalidInput(format!(
+            "Output directory does not exist: {}",
+            dir
+        )));
+    }
+
     let start_time = config
         .start_time
         .as_deref()
@@ -357,18 +1311,58 @@ pub fn validate_task_input(
         }
     }
 
+    if config.video_bitrate_mode == "target_size" {
+        if is_audio_only_container(&config.container) || is_video_only_container(&config.container)
+        {
+            return Err(ConversionError::InvalidInput(format!(
+                "Target size mode is not supported for container '{}'",
+                config.container
+            )));
+        }
+        if !config.target_size_mb.is_some_and(|mb| mb > 0.0) {
+            return Err(ConversionError::InvalidInput(
+                "Target size must be positive".to_string(),
+            ));
+        }
+        if is_lossless_audio_codec(&config.audio_codec) {
+            return Err(ConversionError::InvalidInput(
+                "Target size mode requires a lossy audio codec".to_string(),
+            ));
+        }
+    }
+
+    if config.two_pass && !is_audio_only_container(&config.container) {
+        if !is_bitrate_targeted(&config.video_bitrate_mode) {
+            return Err(ConversionError::InvalidInput(
+                "Two-pass encoding requires a target video bitrate".to_string(),
+            ));
+        }
+        if !supports_two_pass(&config.video_codec) {
+            return Err(ConversionError::InvalidInput(format!(
+                "Two-pass encoding is not supported for video codec '{}'",
+                config.video_codec
+            )));
+        }
+    }
+
     let is_audio_only = is_audio_only_container(&config.container);
     let is_video_only = is_video_only_container(&config.container);
     let supports_audio = container_supports_audio(&config.container);
     let supports_subtitles = container_supports_subtitles(&config.container);
-    if !is_audio_only && !is_video_codec_allowed(&config.container, &config.video_codec) {
+    if !is_audio_only
+        && !is_stream_copy(&config.video_codec)
+        && !is_video_codec_allowed(&config.container, &config.video_codec)
+    {
         return Err(ConversionError::InvalidInput(format!(
             "Video codec '{}' is not compatible with container '{}'",
             config.video_codec, config.container
         )));
     }
 
-    if supports_audio && !is_audio_codec_allowed(&config.container, &config.audio_codec) {
+    if supports_audio
+        && !is_stream_copy(&config.audio_codec)
+        && !is_audio_codec_allowed(&config.container, &config.audio_codec)
+    {
         return Err(ConversionError::InvalidInput(format!(
             "Audio codec '{}' is not compatible with container '{}'",
             config.audio_codec, config.container
@@ -395,6 +1389,12 @@ pub fn validate_task_input(
         ));
     }
 
+    if has_ml_upscale && uses_two_pass(config) {
+        return Err(ConversionError::InvalidInput(
+            "Two-pass encoding is not available together with ML upscaling".to_string(),
+        ));
+    }
+
     if !supports_audio && !config.selected_audio_tracks.is_empty() {
         return Err(ConversionError::InvalidInput(
             "Audio track selection is not available for this container".to_string(),
@@ -413,6 +1413,14 @@ pub fn validate_task_input(
         ));
     }
 
+    if uses_stream_copy(config) {
+        validate_stream_copy(config, probe, has_ml_upscale)?;
+    }
+
+    if !config.segments.is_empty() {
+        validate_segments(config, has_ml_upscale)?;
+    }
+
     if is_video_only {
         if !(2..=256).contains(&config.gif_colors) {
             return Err(ConversionError::InvalidInput(format!(
@@ -432,5 +1440,9 @@ pub fn validate_task_input(
         }
     }
 
+    if !config.additional_outputs.is_empty() {
+        validate_additional_outputs(file_path, config, probe)?;
+    }
+
     Ok(())
 }
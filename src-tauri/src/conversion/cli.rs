@@ -0,0 +1,539 @@
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitCode, Stdio};
+
+use crate::conversion::args::{
+    build_concat_list, build_ffmpeg_args, build_two_pass_args, expected_output_duration,
+    output_template, prepare_config, render_output_template, resolve_output_collision,
+    resolve_output_path, segment_bounds, software_fallback_config, uses_concat_demuxer,
+    uses_keyframe_trim, uses_two_pass, validate_task_input,
+};
+use crate::conversion::diagnostics::{
+    StderrTail, classify_ffmpeg_failure, is_encoder_init_failure,
+};
+use crate::conversion::error::ConversionError;
+use crate::conversion::manager::set_process_priority;
+use crate::conversion::probe::{
+    keyframe_probe_args, parse_keyframe_packets, parse_probe_output, probe_args,
+};
+use crate::conversion::progress::{ProgressParser, estimate_eta};
+use crate::conversion::types::{
+    CollisionPolicy, ConversionConfig, OutputVerification, ProbeMetadata, ProcessPriority,
+    VerificationStatus,
+};
+use crate::conversion::utils::{
+    current_date, finish_partial_output, parse_time, partial_output_path, snap_to_keyframe,
+    task_temp_dir,
+};
+use crate::conversion::verify::{check_verification, verify_output};
+
+const USAGE: &str = "\
+Usage: frame-cli <INPUT> (--config <FILE> | --preset <NAME>) [OPTIONS]
+
+Converts INPUT with the same argument builder and validation as the Frame app.
+
+Options:
+  -c, --config <FILE>     ConversionConfig JSON, or a preset object with a \"config\" field
+  -p, --preset <NAME>     Custom preset saved in the app, matched by id or name
+  -o, --output <PATH>     Output file (default: next to the input, like the app)
+  -n, --name <NAME>       Output file name without extension, placed next to the input
+      --if-exists <MODE>  overwrite (default), skip, increment or fail
+      --ffmpeg <PATH>     ffmpeg binary (default: bundled next to frame-cli, then PATH)
+      --ffprobe <PATH>    ffprobe binary (default: bundled next to frame-cli, then PATH)
+  -q, --quiet             Only print the output path
+  -h, --help              Show this help";
+
+const PRESET_STORE_FILE: &str = "presets.dat";
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum ConfigSource {
+    File(PathBuf),
+    Preset(String),
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) struct CliOptions {
+    pub input: String,
+    pub config: ConfigSource,
+    pub output: Option<String>,
+    pub output_name: Option<String>,
+    pub ffmpeg: Option<PathBuf>,
+    pub ffprobe: Option<PathBuf>,
+    pub collision_policy: CollisionPolicy,
+    pub quiet: bool,
+}
+
+/// Entry point of the `frame-cli` binary. Exit code 2 signals a usage error,
+/// 1 a failed conversion.
+pub fn run(args: Vec<String>) -> ExitCode {
+    if args.iter().any(|a| a == "-h" || a == "--help") {
+        println!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+
+    let options = match parse_cli_args(&args) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("frame-cli: {}\n\n{}", message, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    match convert(&options) {
+        Ok(output_path) => {
+            println!("{}", output_path);
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("frame-cli: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+pub(crate) fn parse_cli_args(args: &[String]) -> Result<CliOptions, String> {
+    let mut input = None;
+    let mut config = None;
+    let mut output = None;
+    let mut output_name = None;
+    let mut ffmpeg = None;
+    let mut ffprobe = None;
+    let mut collision_policy = CollisionPolicy::default();
+    let mut quiet = false;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = |flag: &str| {
+            iter.next()
+                .cloned()
+                .ok_or_else(|| format!("{} expects a value", flag))
+        };
+
+        match arg.as_str() {
+            "-c" | "--config" => config = Some(ConfigSource::File(value(arg)?.into())),
+            "-p" | "--preset" => config = Some(ConfigSource::Preset(value(arg)?)),
+            "-o" | "--output" => output = Some(value(arg)?),
+            "-n" | "--name" => output_name = Some(value(arg)?),
+            "--ffmpeg" => ffmpeg = Some(value(arg)?.into()),
+            "--ffprobe" => ffprobe = Some(value(arg)?.into()),
+            "--if-exists" => {
+                collision_policy = match value(arg)?.as_str() {
+                    "overwrite" => CollisionPolicy::Overwrite,
+                    "skip" => CollisionPolicy::Skip,
+                    "increment" => CollisionPolicy::AutoIncrement,
+                    "fail" => CollisionPolicy::Fail,
+                    other => return Err(format!("unknown --if-exists mode '{}'", other)),
+                }
+            }
+            "-q" | "--quiet" => quiet = true,
+            flag if flag.starts_with('-') && flag.len() > 1 => {
+                return Err(format!("unknown option '{}'", flag));
+            }
+            positional if input.is_none() => input = Some(positional.to_string()),
+            extra => return Err(format!("unexpected argument '{}'", extra)),
+        }
+    }
+
+    if output.is_some() && output_name.is_some() {
+        return Err("--output and --name cannot be combined".to_string());
+    }
+
+    Ok(CliOptions {
+        input: input.ok_or("missing input file")?,
+        config: config.ok_or("either --config or --preset is required")?,
+        output,
+        output_name,
+        ffmpeg,
+        ffprobe,
+        collision_policy,
+        quiet,
+    })
+}
+
+/// Accepts a bare `ConversionConfig` or anything shaped like a saved preset
+/// (`{ "name": ..., "config": { ... } }`), returning the preset name if any.
+pub(crate) fn parse_config_json(
+    raw: &str,
+) -> Result<(ConversionConfig, Option<String>), ConversionError> {
+    let mut value: serde_json::Value = serde_json::from_str(raw)?;
+    let Some(config) = value.get_mut("config").map(serde_json::Value::take) else {
+        return Ok((serde_json::from_value(value)?, None));
+    };
+    let name = value
+        .get("name")
+        .and_then(serde_json::Value::as_str)
+        .map(str::to_string);
+    Ok((serde_json::from_value(config)?, name))
+}
+
+/// Looks a preset up in the store the app writes custom presets to.
+pub(crate) fn find_preset(
+    store_json: &str,
+    name: &str,
+) -> Result<(ConversionConfig, Option<String>), ConversionError> {
+    let store: serde_json::Value = serde_json::from_str(store_json)?;
+    let preset = store
+        .get("presets")
+        .and_then(serde_json::Value::as_array)
+        .into_iter()
+        .flatten()
+        .find(|preset| {
+            ["id", "name"].iter().any(|key| {
+                preset
+                    .get(*key)
+                    .and_then(serde_json::Value::as_str)
+                    .is_some_and(|value| value.eq_ignore_ascii_case(name))
+            })
+        })
+        .ok_or_else(|| ConversionError::InvalidInput(format!("Preset '{}' not found", name)))?;
+
+    parse_config_json(&preset.to_string())
+}
+
+fn load_config(
+    source: &ConfigSource,
+) -> Result<(ConversionConfig, Option<String>), ConversionError> {
+    match source {
+        ConfigSource::File(path) => parse_config_json(&std::fs::read_to_string(path)?),
+        ConfigSource::Preset(name) => {
+            let store_path = app_data_dir()
+                .map(|dir| dir.join(PRESET_STORE_FILE))
+                .ok_or_else(|| {
+                    ConversionError::InvalidInput(
+                        "Could not locate the Frame data directory".to_string(),
+                    )
+                })?;
+            let raw = std::fs::read_to_string(&store_path).map_err(|e| {
+                ConversionError::InvalidInput(format!(
+                    "Could not read presets from {}: {}",
+                    store_path.display(),
+                    e
+                ))
+            })?;
+            find_preset(&raw, name)
+        }
+    }
+}
+
+/// Mirrors Tauri's app data directory for the `Frame` bundle identifier.
+fn app_data_dir() -> Option<PathBuf> {
+    #[cfg(target_os = "windows")]
+    let base = std::env::var_os("APPDATA").map(PathBuf::from);
+    #[cfg(target_os = "macos")]
+    let base = std::env::var_os("HOME")
+        .map(|home| PathBuf::from(home).join("Library/Application Support"));
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let base = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")));
+
+    base.map(|dir| dir.join("Frame"))
+}
+
+/// Explicit path first, then a sidecar shipped next to `frame-cli`, then `PATH`.
+fn resolve_tool(name: &str, explicit: Option<&Path>) -> PathBuf {
+    if let Some(path) = explicit {
+        return path.to_path_buf();
+    }
+
+    let file_name = format!("{}{}", name, std::env::consts::EXE_SUFFIX);
+    std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|dir| dir.join(&file_name)))
+        .filter(|candidate| candidate.is_file())
+        .unwrap_or_else(|| PathBuf::from(file_name))
+}
+
+fn run_ffprobe(ffprobe: &Path, args: Vec<String>) -> Result<String, ConversionError> {
+    let output = Command::new(ffprobe)
+        .args(args)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| ConversionError::Shell(format!("{}: {}", ffprobe.display(), e)))?;
+
+    if !output.status.success() {
+        return Err(ConversionError::Probe(
+            String::from_utf8_lossy(&output.stderr).to_string(),
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+fn snap_cut(ffprobe: &Path, input: &str, start: &str) -> String {
+    let Some(requested) = parse_time(start) else {
+        return start.to_string();
+    };
+
+    run_ffprobe(ffprobe, keyframe_probe_args(input, requested))
+        .ok()
+        .and_then(|out| snap_to_keyframe(requested, &parse_keyframe_packets(&out)))
+        .map_or_else(|| start.to_string(), |snapped| format!("{:.6}", snapped))
+}
+
+fn convert(options: &CliOptions) -> Result<String, ConversionError> {
+    let ffmpeg = resolve_tool("ffmpeg", options.ffmpeg.as_deref());
+    let ffprobe = resolve_tool("ffprobe", options.ffprobe.as_deref());
+    let input = options.input.as_str();
+    let (mut config, preset_name) = load_config(&options.config)?;
+
+    if config
+        .ml_upscale
+        .as_deref()
+        .is_some_and(|mode| !mode.is_empty() && mode != "none")
+    {
+        return Err(ConversionError::InvalidInput(
+            "ML upscaling is only available in the desktop app".to_string(),
+        ));
+    }
+    if !config.additional_outputs.is_empty() {
+        return Err(ConversionError::InvalidInput(
+            "Additional outputs are only available in the desktop app".to_string(),
+        ));
+    }
+
+    let probe: Option<ProbeMetadata> = run_ffprobe(&ffprobe, probe_args(input))
+        .and_then(|out| parse_probe_output(&out))
+        .ok();
+    validate_task_input(input, &config, probe.as_ref())?;
+
+    let output_name = match output_template(&config).filter(|_| options.output_name.is_none()) {
+        Some(template) => Some(render_output_template(
+            template,
+            input,
+            &config,
+            probe.as_ref(),
+            preset_name.as_deref(),
+            &current_date(),
+        )?),
+        None => options.output_name.clone(),
+    };
+    let requested_path = options
+        .output
+        .clone()
+        .unwrap_or_else(|| resolve_output_path(input, &config, output_name));
+    let Some(output_path) = resolve_output_collision(
+        &requested_path,
+        options.collision_policy,
+        &Default::default(),
+        |path| Path::new(path).exists(),
+    )?
+    else {
+        if !options.quiet {
+            eprintln!("Skipped: {} already exists", requested_path);
+        }
+        return Ok(requested_path);
+    };
+
+    if uses_keyframe_trim(&config) {
+        if config.segments.is_empty() {
+            let start = config.start_time.clone().unwrap_or_default();
+            config.start_time = Some(snap_cut(&ffprobe, input, &start));
+        }
+        for segment in config.segments.iter_mut() {
+            segment.start = snap_cut(&ffprobe, input, &segment.start);
+        }
+    }
+
+    let duration = expected_output_duration(&config, probe.as_ref());
+    let mut config = prepare_config(&config, probe.as_ref())?;
+
+    let run_id = format!("cli_{}", std::process::id());
+    let concat_dir = task_temp_dir("concat", &run_id);
+    let passlog_dir = task_temp_dir("passlog", &run_id);
+    let partial_path = partial_output_path(&output_path, &run_id);
+    let encode_path = partial_path.to_string_lossy().to_string();
+
+    let encode = |input_path: &str, config: &ConversionConfig| {
+        let priority = config.process_priority;
+        if uses_two_pass(config) {
+            std::fs::create_dir_all(&passlog_dir)?;
+            let log_prefix = passlog_dir.join("ffmpeg2pass");
+            let [first, second] = build_two_pass_args(
+                input_path,
+                &encode_path,
+                config,
+                &log_prefix.to_string_lossy(),
+            );
+            run_pass(
+                &ffmpeg,
+                first,
+                duration,
+                (0.0, 50.0),
+                priority,
+                options.quiet,
+            )?;
+            run_pass(
+                &ffmpeg,
+                second,
+                duration,
+                (50.0, 50.0),
+                priority,
+                options.quiet,
+            )
+        } else {
+            let args = build_ffmpeg_args(input_path, &encode_path, config);
+            run_pass(
+                &ffmpeg,
+                args,
+                duration,
+                (0.0, 100.0),
+                priority,
+                options.quiet,
+            )
+        }
+    };
+
+    let result = (|| {
+        let input_path = if uses_concat_demuxer(&config) {
+            std::fs::create_dir_all(&concat_dir)?;
+            let list_path = concat_dir.join("segments.ffconcat");
+            std::fs::write(
+                &list_path,
+                build_concat_list(input, &segment_bounds(&config)),
+            )?;
+            list_path.to_string_lossy().to_string()
+        } else {
+            input.to_string()
+        };
+
+        match encode(&input_path, &config) {
+            Err(err) if is_encoder_init_failure(&err) => {
+                let Some(fallback) = software_fallback_config(&config) else {
+                    return Err(err);
+                };
+                eprintln!(
+                    "fallback: {} failed to initialize, retrying with {}: {}",
+                    config.video_codec, fallback.video_codec, err
+                );
+                config = fallback;
+                encode(&input_path, &config)
+            }
+            result => result,
+        }
+    })();
+
+    for dir in [&concat_dir, &passlog_dir] {
+        if dir.exists() {
+            let _ = std::fs::remove_dir_all(dir);
+        }
+    }
+
+    let finished = finish_partial_output(&partial_path, &output_path, result.is_ok());
+    result?;
+    finished?;
+
+    let verification = match run_ffprobe(&ffprobe, probe_args(&output_path))
+        .and_then(|out| parse_probe_output(&out))
+    {
+        Ok(output) => verify_output(&config, probe.as_ref(), duration, &output),
+        Err(e) => OutputVerification {
+            status: VerificationStatus::Failed,
+            issues: vec![format!("Output could not be probed: {}", e)],
+        },
+    };
+    for issue in &verification.issues {
+        eprintln!("verify: {}", issue);
+    }
+    check_verification(&output_path, &verification)?;
+    Ok(output_path)
+}
+
+/// Runs one ffmpeg invocation, drawing `span` (offset, share) of the overall
+/// progress bar on stderr. On failure the tail of ffmpeg's log is printed and
+/// the error is classified from it.
+fn run_pass(
+    ffmpeg: &Path,
+    ffmpeg_args: Vec<String>,
+    duration: f64,
+    span: (f64, f64),
+    priority: ProcessPriority,
+    quiet: bool,
+) -> Result<(), ConversionError> {
+    let mut child = Command::new(ffmpeg)
+        .args(["-progress", "pipe:1", "-nostats"])
+        .args(ffmpeg_args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| ConversionError::Shell(format!("{}: {}", ffmpeg.display(), e)))?;
+    if let Err(e) = set_process_priority(child.id(), priority) {
+        eprintln!("frame-cli: {}", e);
+    }
+
+    let stderr = child.stderr.take().map(|stderr| {
+        std::thread::spawn(move || {
+            let mut tail = StderrTail::default();
+            for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+                tail.push(&line);
+            }
+            tail
+        })
+    });
+
+    let started_at = std::time::Instant::now();
+    let mut parser = ProgressParser::default();
+    if let Some(stdout) = child.stdout.take() {
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            let Some(update) = parser.push_line(&line) else {
+                continue;
+            };
+            if quiet || duration <= 0.0 {
+                continue;
+            }
+
+            let out_time = update.out_time.unwrap_or(0.0);
+            let fraction = if update.finished {
+                1.0
+            } else {
+                (out_time / duration).min(1.0)
+            };
+            let remaining_media = if span.0 == 0.0 && span.1 < 100.0 {
+                duration
+            } else {
+                0.0
+            };
+            let eta = estimate_eta(
+                duration + remaining_media,
+                out_time,
+                update.speed,
+                started_at.elapsed().as_secs_f64(),
+            );
+            eprint!(
+                "\r{:>5.1}%  speed {:>5}  eta {:>8}",
+                span.0 + fraction * span.1,
+                update
+                    .speed
+                    .map_or_else(|| "-".to_string(), |s| format!("{:.2}x", s)),
+                eta.map_or_else(|| "-".to_string(), format_clock),
+            );
+            let _ = std::io::stderr().flush();
+        }
+    }
+
+    let status = child.wait()?;
+    let log_tail = stderr
+        .and_then(|handle| handle.join().ok())
+        .unwrap_or_default();
+    if !quiet && duration > 0.0 {
+        eprintln!();
+    }
+
+    if status.success() {
+        return Ok(());
+    }
+    if !quiet {
+        eprintln!("{}", log_tail.join());
+    }
+    Err(classify_ffmpeg_failure(&log_tail, status.code()))
+}
+
+fn format_clock(seconds: f64) -> String {
+    let total = seconds.round() as u64;
+    format!(
+        "{:02}:{:02}:{:02}",
+        total / 3600,
+        (total % 3600) / 60,
+        total % 60
+    )
+}
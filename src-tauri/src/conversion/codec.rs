@@ -1,5 +1,8 @@
 use crate::conversion::types::ConversionConfig;
-use crate::conversion::utils::{is_nvenc_codec, is_videotoolbox_codec, map_nvenc_preset};
+use crate::conversion::utils::{
+    is_bitrate_targeted, is_lossless_audio_codec, is_nvenc_codec, is_stream_copy,
+    is_videotoolbox_codec, map_nvenc_preset, nvenc_cq,
+};
 
 pub fn add_video_codec_args(args: &mut Vec<String>, config: &ConversionConfig) {
     let is_nvenc = is_nvenc_codec(&config.video_codec);
@@ -8,13 +11,15 @@ pub fn add_video_codec_args(args: &mut Vec<String>, config: &ConversionConfig) {
     args.push("-c:v".to_string());
     args.push(config.video_codec.clone());
 
-    if config.video_bitrate_mode == "bitrate" {
+    if is_stream_copy(&config.video_codec) {
+        return;
+    }
+
+    if is_bitrate_targeted(&config.video_bitrate_mode) {
         args.push("-b:v".to_string());
         args.push(format!("{}k", config.video_bitrate));
     } else if is_nvenc {
-        let cq = (52.0 - (config.quality as f64 / 2.0))
-            .round()
-            .clamp(1.0, 51.0) as u32;
+        let cq = nvenc_cq(config.quality);
         args.push("-rc:v".to_string());
         args.push("vbr".to_string());
         args.push("-cq:v".to_string());
@@ -54,12 +59,37 @@ pub fn add_video_codec_args(args: &mut Vec<String>, config: &ConversionConfig) {
     }
 }
 
+pub fn add_two_pass_args(
+    args: &mut Vec<String>,
+    config: &ConversionConfig,
+    pass: u8,
+    log_prefix: &str,
+) {
+    // libx265 ignores -pass/-passlogfile and takes its stats file through x265-params,
+    // where ':' separates options and must be escaped in the path.
+    if config.video_codec == "libx265" {
+        let stats_path = format!("{}.log", log_prefix)
+            .replace('\\', "/")
+            .replace(':', "\\:");
+        args.push("-x265-params".to_string());
+        args.push(format!("pass={}:stats={}", pass, stats_path));
+    } else {
+        args.push("-pass".to_string());
+        args.push(pass.to_string());
+        args.push("-passlogfile".to_string());
+        args.push(log_prefix.to_string());
+    }
+}
+
 pub fn add_audio_codec_args(args: &mut Vec<String>, config: &ConversionConfig) {
     args.push("-c:a".to_string());
     args.push(config.audio_codec.clone());
 
-    let lossless_audio_codecs = ["flac", "alac", "pcm_s16le"];
-    if !lossless_audio_codecs.contains(&config.audio_codec.as_str()) {
+    if is_stream_copy(&config.audio_codec) {
+        return;
+    }
+
+    if !is_lossless_audio_codec(&config.audio_codec) {
         args.push("-b:a".to_string());
         args.push(format!("{}k", config.audio_bitrate));
     }
@@ -1,12 +1,21 @@
 use tauri::{AppHandle, command};
+use tokio::sync::oneshot;
 
-use crate::conversion::args::validate_task_input;
+use crate::conversion::args::{
+    output_template, render_output_template, uses_stream_copy, validate_task_input,
+};
 use crate::conversion::error::ConversionError;
-use crate::conversion::manager::{ConversionManager, ManagerMessage};
+use crate::conversion::manager::{ConversionManager, ManagerMessage, validate_retry_policy};
+use crate::conversion::pipeline::validate_pipeline;
 use crate::conversion::probe::probe_media_file;
-use crate::conversion::types::{ConversionConfig, ConversionTask, ProbeMetadata};
+use crate::conversion::types::{
+    CollisionPolicy, ConversionConfig, ConversionTask, JobLink, PipelineStep, ProbeMetadata,
+    QueuePosition, QueueSchedule, QueueState, RetryPolicy, SchedulerConfig,
+};
+use crate::conversion::utils::{current_date, task_temp_dir};
 
 #[command]
+#[allow(clippy::too_many_arguments)]
 pub async fn queue_conversion(
     app: AppHandle,
     manager: tauri::State<'_, ConversionManager>,
@@ -14,22 +23,34 @@ pub async fn queue_conversion(
     file_path: String,
     output_name: Option<String>,
     config: ConversionConfig,
+    priority: Option<i32>,
+    collision_policy: Option<CollisionPolicy>,
+    preset_name: Option<String>,
+    retry_policy: Option<RetryPolicy>,
 ) -> Result<(), ConversionError> {
-    validate_task_input(&file_path, &config)?;
-
-    if let Some(mode) = config
-        .ml_upscale
-        .as_deref()
-        .filter(|mode| !mode.is_empty() && *mode != "none")
-    {
-        crate::conversion::upscale::validate_upscale_runtime(&app, mode).await?;
+    if let Some(policy) = &retry_policy {
+        validate_retry_policy(policy)?;
     }
+    let output_name = prepare_task(
+        &app,
+        &file_path,
+        output_name,
+        &config,
+        preset_name.as_deref(),
+        None,
+    )
+    .await?;
 
     let task = ConversionTask {
         id,
         file_path,
         output_name,
         config,
+        priority: priority.unwrap_or(0),
+        collision_policy: collision_policy.unwrap_or_default(),
+        retry_policy,
+        attempts: 0,
+        job: None,
     };
 
     manager
@@ -40,6 +61,137 @@ pub async fn queue_conversion(
     Ok(())
 }
 
+/// Queues a multi-step job on `file_path`. Each step becomes a task that
+/// starts once the steps it depends on complete; intermediate outputs without
+/// an output directory are written to a temp directory and deleted once no
+/// step reads them. Other outputs are named after `file_path`. The job is
+/// refused if a step id is already used by a pending task.
+#[command]
+#[allow(clippy::too_many_arguments)]
+pub async fn queue_pipeline(
+    app: AppHandle,
+    manager: tauri::State<'_, ConversionManager>,
+    job_id: String,
+    file_path: String,
+    steps: Vec<PipelineStep>,
+    priority: Option<i32>,
+    collision_policy: Option<CollisionPolicy>,
+    preset_name: Option<String>,
+) -> Result<(), ConversionError> {
+    validate_pipeline(&steps)?;
+
+    let source_probe = if steps
+        .iter()
+        .any(|step| needs_probe(&step.config, step.output_name.as_deref()))
+    {
+        Some(probe_media_file(&app, &file_path).await?)
+    } else {
+        None
+    };
+
+    let mut tasks = Vec::with_capacity(steps.len());
+    for step in steps {
+        let mut config = step.config;
+        if step.intermediate && config.output_directory.is_none() {
+            let dir = task_temp_dir("pipeline", &job_id);
+            std::fs::create_dir_all(&dir)?;
+            config.output_directory = Some(dir.to_string_lossy().to_string());
+        }
+
+        // Every step is checked against the source, since the input of a step
+        // reading an earlier output does not exist yet.
+        let output_name = prepare_task(
+            &app,
+            &file_path,
+            step.output_name,
+            &config,
+            preset_name.as_deref(),
+            source_probe.as_ref(),
+        )
+        .await?;
+
+        tasks.push(ConversionTask {
+            id: step.id,
+            file_path: file_path.clone(),
+            output_name,
+            config,
+            priority: priority.unwrap_or(0),
+            collision_policy: collision_policy.unwrap_or_default(),
+            retry_policy: None,
+            attempts: 0,
+            job: Some(JobLink {
+                job_id: job_id.clone(),
+                depends_on: step.depends_on,
+                input_from: step.input_from,
+                intermediate: step.intermediate,
+                source_path: file_path.clone(),
+            }),
+        });
+    }
+
+    let (reply_tx, reply_rx) = oneshot::channel();
+    manager
+        .sender
+        .send(ManagerMessage::EnqueueJob(tasks, reply_tx))
+        .await
+        .map_err(|e| ConversionError::Channel(e.to_string()))?;
+    reply_rx
+        .await
+        .map_err(|e| ConversionError::Channel(e.to_string()))?
+}
+
+/// Whether validating or naming a task needs the input's probe: stream copy
+/// is checked against the source streams and templates read its metadata.
+fn needs_probe(config: &ConversionConfig, output_name: Option<&str>) -> bool {
+    uses_stream_copy(config) || (output_name.is_none() && output_template(config).is_some())
+}
+
+/// Validates a task and expands its output template, returning the output
+/// name to queue it with. `file_path` is probed when needed unless
+/// `source_probe` already holds its probe.
+async fn prepare_task(
+    app: &AppHandle,
+    file_path: &str,
+    output_name: Option<String>,
+    config: &ConversionConfig,
+    preset_name: Option<&str>,
+    source_probe: Option<&ProbeMetadata>,
+) -> Result<Option<String>, ConversionError> {
+    let probe = match source_probe {
+        Some(probe) => Some(probe.clone()),
+        None if needs_probe(config, output_name.as_deref()) => {
+            Some(probe_media_file(app, file_path).await?)
+        }
+        None => None,
+    };
+    let template = output_template(config).filter(|_| output_name.is_none());
+    validate_task_input(file_path, config, probe.as_ref())?;
+
+    // Templates are expanded once at queue time; the manager still resolves
+    // collisions against the rendered name when the task starts.
+    let output_name = match template {
+        Some(template) => Some(render_output_template(
+            template,
+            file_path,
+            config,
+            probe.as_ref(),
+            preset_name,
+            &current_date(),
+        )?),
+        None => output_name,
+    };
+
+    if let Some(mode) = config
+        .ml_upscale
+        .as_deref()
+        .filter(|mode| !mode.is_empty() && *mode != "none")
+    {
+        crate::conversion::upscale::validate_upscale_runtime(app, mode).await?;
+    }
+
+    Ok(output_name)
+}
+
 #[command]
 pub async fn pause_conversion(
     manager: tauri::State<'_, ConversionManager>,
@@ -64,6 +216,28 @@ pub async fn cancel_conversion(
     manager.cancel_task(&id)
 }
 
+#[command]
+pub async fn pause_all_conversions(
+    manager: tauri::State<'_, ConversionManager>,
+) -> Result<(), ConversionError> {
+    manager.pause_all()
+}
+
+#[command]
+pub async fn resume_all_conversions(
+    manager: tauri::State<'_, ConversionManager>,
+) -> Result<(), ConversionError> {
+    manager.resume_all()
+}
+
+#[command]
+pub async fn stop_conversion(
+    manager: tauri::State<'_, ConversionManager>,
+    id: String,
+) -> Result<(), ConversionError> {
+    manager.stop_task(&id)
+}
+
 #[command]
 pub async fn probe_media(
     app: AppHandle,
@@ -86,3 +260,141 @@ pub fn set_max_concurrency(
 ) -> Result<(), ConversionError> {
     manager.update_max_concurrency(value)
 }
+
+#[command]
+pub fn get_retry_policy(
+    manager: tauri::State<'_, ConversionManager>,
+) -> Result<RetryPolicy, ConversionError> {
+    Ok(manager.current_retry_policy())
+}
+
+#[command]
+pub fn set_retry_policy(
+    manager: tauri::State<'_, ConversionManager>,
+    policy: RetryPolicy,
+) -> Result<(), ConversionError> {
+    manager.update_retry_policy(policy)
+}
+
+#[command]
+pub fn get_scheduler_config(
+    manager: tauri::State<'_, ConversionManager>,
+) -> Result<SchedulerConfig, ConversionError> {
+    Ok(manager.current_scheduler())
+}
+
+#[command]
+pub fn set_scheduler_config(
+    manager: tauri::State<'_, ConversionManager>,
+    scheduler: SchedulerConfig,
+) -> Result<(), ConversionError> {
+    manager.update_scheduler(scheduler)
+}
+
+#[command]
+pub fn get_queue_schedule(
+    manager: tauri::State<'_, ConversionManager>,
+) -> Result<QueueSchedule, ConversionError> {
+    Ok(manager.current_schedule())
+}
+
+#[command]
+pub fn set_queue_schedule(
+    manager: tauri::State<'_, ConversionManager>,
+    schedule: QueueSchedule,
+) -> Result<(), ConversionError> {
+    manager.update_schedule(schedule)
+}
+
+#[command]
+pub async fn get_interrupted_conversions(
+    manager: tauri::State<'_, ConversionManager>,
+) -> Result<Vec<ConversionTask>, ConversionError> {
+    let (reply_tx, reply_rx) = oneshot::channel();
+    manager
+        .sender
+        .send(ManagerMessage::ListInterrupted(reply_tx))
+        .await
+        .map_err(|e| ConversionError::Channel(e.to_string()))?;
+    reply_rx
+        .await
+        .map_err(|e| ConversionError::Channel(e.to_string()))
+}
+
+#[command]
+pub async fn requeue_interrupted_conversions(
+    manager: tauri::State<'_, ConversionManager>,
+    ids: Option<Vec<String>>,
+) -> Result<(), ConversionError> {
+    manager
+        .sender
+        .send(ManagerMessage::RequeueInterrupted(ids))
+        .await
+        .map_err(|e| ConversionError::Channel(e.to_string()))?;
+    Ok(())
+}
+
+#[command]
+pub async fn get_queue_state(
+    manager: tauri::State<'_, ConversionManager>,
+) -> Result<QueueState, ConversionError> {
+    let (reply_tx, reply_rx) = oneshot::channel();
+    manager
+        .sender
+        .send(ManagerMessage::GetQueueState(reply_tx))
+        .await
+        .map_err(|e| ConversionError::Channel(e.to_string()))?;
+    reply_rx
+        .await
+        .map_err(|e| ConversionError::Channel(e.to_string()))
+}
+
+#[command]
+pub async fn move_queued_conversion(
+    manager: tauri::State<'_, ConversionManager>,
+    id: String,
+    position: QueuePosition,
+) -> Result<(), ConversionError> {
+    let (reply_tx, reply_rx) = oneshot::channel();
+    manager
+        .sender
+        .send(ManagerMessage::MoveTask(id, position, reply_tx))
+        .await
+        .map_err(|e| ConversionError::Channel(e.to_string()))?;
+    reply_rx
+        .await
+        .map_err(|e| ConversionError::Channel(e.to_string()))?
+}
+
+#[command]
+pub async fn set_conversion_priority(
+    manager: tauri::State<'_, ConversionManager>,
+    id: String,
+    priority: i32,
+) -> Result<(), ConversionError> {
+    let (reply_tx, reply_rx) = oneshot::channel();
+    manager
+        .sender
+        .send(ManagerMessage::SetPriority(id, priority, reply_tx))
+        .await
+        .map_err(|e| ConversionError::Channel(e.to_string()))?;
+    reply_rx
+        .await
+        .map_err(|e| ConversionError::Channel(e.to_string()))?
+}
+
+#[command]
+pub async fn remove_queued_conversion(
+    manager: tauri::State<'_, ConversionManager>,
+    id: String,
+) -> Result<(), ConversionError> {
+    let (reply_tx, reply_rx) = oneshot::channel();
+    manager
+        .sender
+        .send(ManagerMessage::RemoveTask(id, reply_tx))
+        .await
+        .map_err(|e| ConversionError::Channel(e.to_string()))?;
+    reply_rx
+        .await
+        .map_err(|e| ConversionError::Channel(e.to_string()))?
+}
@@ -0,0 +1,148 @@
+use std::collections::VecDeque;
+
+use crate::conversion::error::ConversionError;
+
+/// How many stderr lines are kept per ffmpeg invocation for diagnosis.
+pub const STDERR_TAIL_LINES: usize = 40;
+
+/// Ring buffer over the last lines ffmpeg wrote to stderr. The fatal message
+/// is almost always within the final few lines, after the stream summary.
+#[derive(Debug)]
+pub struct StderrTail {
+    lines: VecDeque<String>,
+    capacity: usize,
+}
+
+impl Default for StderrTail {
+    fn default() -> Self {
+        Self::with_capacity(STDERR_TAIL_LINES)
+    }
+}
+
+impl StderrTail {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            lines: VecDeque::with_capacity(capacity),
+            capacity: capacity.max(1),
+        }
+    }
+
+    pub fn push(&mut self, line: &str) {
+        let line = line.trim();
+        if line.is_empty() {
+            return;
+        }
+        if self.lines.len() == self.capacity {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(line.to_string());
+    }
+
+    pub fn lines(&self) -> impl DoubleEndedIterator<Item = &str> {
+        self.lines.iter().map(String::as_str)
+    }
+
+    pub fn join(&self) -> String {
+        self.lines().collect::<Vec<_>>().join("\n")
+    }
+}
+
+type ErrorCtor = fn(String) -> ConversionError;
+
+/// Known fatal messages, most specific first. NVENC reports an exhausted
+/// session pool as an out-of-memory or client-key error when opening the
+/// encoder, so those are checked before the generic encoder failures.
+const FAILURE_PATTERNS: &[(&str, ErrorCtor)] = &[
+    (
+        "openencodesessionex failed: out of memory",
+        ConversionError::NvencSessionLimit,
+    ),
+    (
+        "openencodesessionex failed: incompatible client key",
+        ConversionError::NvencSessionLimit,
+    ),
+    (
+        "too many concurrent sessions",
+        ConversionError::NvencSessionLimit,
+    ),
+    ("unknown encoder", ConversionError::EncoderUnavailable),
+    ("encoder not found", ConversionError::EncoderUnavailable),
+    (
+        "cannot load libnvidia-encode",
+        ConversionError::EncoderUnavailable,
+    ),
+    (
+        "cannot load nvencodeapi",
+        ConversionError::EncoderUnavailable,
+    ),
+    ("cannot load libcuda", ConversionError::EncoderUnavailable),
+    ("cannot load nvcuda", ConversionError::EncoderUnavailable),
+    (
+        "no capable devices found",
+        ConversionError::EncoderUnavailable,
+    ),
+    (
+        "no nvenc capable devices found",
+        ConversionError::EncoderUnavailable,
+    ),
+    (
+        "driver does not support the required nvenc api version",
+        ConversionError::EncoderUnavailable,
+    ),
+    (
+        "initializeencoder failed",
+        ConversionError::EncoderUnavailable,
+    ),
+    ("no space left on device", ConversionError::DiskFull),
+    ("not enough space on the disk", ConversionError::DiskFull),
+    ("disk quota exceeded", ConversionError::DiskFull),
+    ("permission denied", ConversionError::PermissionDenied),
+    ("access is denied", ConversionError::PermissionDenied),
+    ("read-only file system", ConversionError::PermissionDenied),
+    (
+        "could not find tag for codec",
+        ConversionError::UnsupportedCodec,
+    ),
+    (
+        "codec not currently supported in container",
+        ConversionError::UnsupportedCodec,
+    ),
+    (
+        "incompatible with output codec",
+        ConversionError::UnsupportedCodec,
+    ),
+    (
+        "invalid data found when processing input",
+        ConversionError::InvalidData,
+    ),
+    ("moov atom not found", ConversionError::InvalidData),
+    ("ebml header parsing failed", ConversionError::InvalidData),
+];
+
+/// Maps the stderr of a failed ffmpeg run to a structured error carrying the
+/// matching line. Unrecognised failures keep the exit code and the last line.
+pub fn classify_ffmpeg_failure(tail: &StderrTail, exit_code: Option<i32>) -> ConversionError {
+    let matched = FAILURE_PATTERNS.iter().find_map(|(pattern, ctor)| {
+        tail.lines()
+            .rev()
+            .find(|line| line.to_ascii_lowercase().contains(pattern))
+            .map(|line| ctor(line.to_string()))
+    });
+
+    matched.unwrap_or_else(|| {
+        let message = format!("Process terminated with code {:?}", exit_code);
+        ConversionError::Worker(match tail.lines().next_back() {
+            Some(last) => format!("{}: {}", message, last),
+            None => message,
+        })
+    })
+}
+
+/// Whether a failure happened while the encoder was being set up, before any
+/// frame was written, so retrying with a different encoder can succeed.
+pub fn is_encoder_init_failure(err: &ConversionError) -> bool {
+    matches!(
+        err,
+        ConversionError::EncoderUnavailable(_) | ConversionError::NvencSessionLimit(_)
+    )
+}
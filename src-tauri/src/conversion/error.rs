@@ -19,6 +19,59 @@ pub enum ConversionError {
     InvalidInput(String),
     #[error("Task not found: {0}")]
     TaskNotFound(String),
+    #[error("Output already exists: {0}")]
+    OutputExists(String),
+    #[error("Encoder unavailable: {0}")]
+    EncoderUnavailable(String),
+    #[error("Out of disk space: {0}")]
+    DiskFull(String),
+    #[error("Permission denied: {0}")]
+    PermissionDenied(String),
+    #[error("Invalid data in input: {0}")]
+    InvalidData(String),
+    #[error("Codec not supported by the output container: {0}")]
+    UnsupportedCodec(String),
+    #[error("NVENC session limit reached: {0}")]
+    NvencSessionLimit(String),
+    #[error("Task cancelled: {0}")]
+    Cancelled(String),
+}
+
+impl ConversionError {
+    /// Stable identifier sent to the frontend alongside the message.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ConversionError::Shell(_) => "shell",
+            ConversionError::Io(_) => "io",
+            ConversionError::Json(_) => "json",
+            ConversionError::Channel(_) => "channel",
+            ConversionError::Probe(_) => "probe",
+            ConversionError::Worker(_) => "worker",
+            ConversionError::InvalidInput(_) => "invalid_input",
+            ConversionError::TaskNotFound(_) => "task_not_found",
+            ConversionError::OutputExists(_) => "output_exists",
+            ConversionError::EncoderUnavailable(_) => "encoder_unavailable",
+            ConversionError::DiskFull(_) => "disk_full",
+            ConversionError::PermissionDenied(_) => "permission_denied",
+            ConversionError::InvalidData(_) => "invalid_data",
+            ConversionError::UnsupportedCodec(_) => "unsupported_codec",
+            ConversionError::NvencSessionLimit(_) => "nvenc_session_limit",
+            ConversionError::Cancelled(_) => "cancelled",
+        }
+    }
+
+    /// The ffmpeg output line a failure was classified from, if any.
+    pub fn excerpt(&self) -> Option<&str> {
+        match self {
+            ConversionError::EncoderUnavailable(line)
+            | ConversionError::DiskFull(line)
+            | ConversionError::PermissionDenied(line)
+            | ConversionError::InvalidData(line)
+            | ConversionError::UnsupportedCodec(line)
+            | ConversionError::NvencSessionLimit(line) => Some(line),
+            _ => None,
+        }
+    }
 }
 
 impl Serialize for ConversionError {
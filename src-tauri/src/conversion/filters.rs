@@ -43,7 +43,16 @@ pub fn build_video_filters(config: &ConversionConfig, include_scale: bool) -> Ve
         }
     }
 
-    if include_scale && (config.resolution != "original" || config.resolution == "custom") {
+    if include_scale {
+        filters.extend(build_scale_filter(config));
+    }
+
+    filters
+}
+
+/// The `scale` step for the configured resolution, if it changes the size.
+pub fn build_scale_filter(config: &ConversionConfig) -> Option<String> {
+    if config.resolution != "original" || config.resolution == "custom" {
         let algorithm = match config.scaling_algorithm.as_str() {
             "lanczos" => ":flags=lanczos",
             "bilinear" => ":flags=bilinear",
@@ -76,10 +85,10 @@ pub fn build_video_filters(config: &ConversionConfig, include_scale: bool) -> Ve
             }
         };
 
-        filters.push(scale_filter);
+        return Some(scale_filter);
     }
 
-    filters
+    None
 }
 
 pub fn build_audio_filters(config: &ConversionConfig) -> Vec<String> {
@@ -100,45 +109,14 @@ pub fn build_audio_filters(config: &ConversionConfig) -> Vec<String> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::conversion::tests::test_helpers;
     use crate::conversion::types::CropConfig;
 
     fn default_config() -> ConversionConfig {
         ConversionConfig {
-            container: "mp4".to_string(),
-            video_codec: "libx264".to_string(),
-            video_bitrate_mode: "crf".to_string(),
-            video_bitrate: "5000".to_string(),
-            audio_codec: "aac".to_string(),
             audio_bitrate: "192".to_string(),
-            audio_channels: "original".to_string(),
-            audio_volume: 100.0,
-            audio_normalize: false,
-            selected_audio_tracks: vec![],
-            selected_subtitle_tracks: vec![],
-            subtitle_burn_path: None,
-            resolution: "original".to_string(),
-            custom_width: None,
-            custom_height: None,
             scaling_algorithm: "lanczos".to_string(),
-            fps: "original".to_string(),
-            crf: 23,
-            quality: 50,
-            preset: "medium".to_string(),
-            start_time: None,
-            end_time: None,
-            metadata: Default::default(),
-            rotation: "0".to_string(),
-            flip_horizontal: false,
-            flip_vertical: false,
-            ml_upscale: None,
-            crop: None,
-            nvenc_spatial_aq: false,
-            nvenc_temporal_aq: false,
-            videotoolbox_allow_sw: false,
-            hw_decode: false,
-            gif_colors: 256,
-            gif_dither: "sierra2_4a".to_string(),
-            gif_loop: 0,
+            ..test_helpers::config()
         }
     }
 
@@ -1,12 +1,14 @@
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::{
     Arc, Mutex,
-    atomic::{AtomicUsize, Ordering},
+    atomic::{AtomicBool, AtomicUsize, Ordering},
 };
-use tauri::{AppHandle, Emitter};
-use tokio::sync::mpsc;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::{mpsc, oneshot, watch};
 
-use crate::conversion::types::{ErrorPayload, LogPayload};
+use crate::conversion::types::{
+    CompletedPayload, ErrorPayload, LogPayload, PipelinePayload, RetryPayload, SchedulePayload,
+};
 
 #[cfg(unix)]
 use libc;
@@ -17,233 +19,289 @@ use windows::{
         Foundation::{CloseHandle, HANDLE},
         System::{
             LibraryLoader::{GetModuleHandleA, GetProcAddress},
-            Threading::{OpenProcess, PROCESS_SUSPEND_RESUME},
+            Threading::{
+                ABOVE_NORMAL_PRIORITY_CLASS, BELOW_NORMAL_PRIORITY_CLASS, NORMAL_PRIORITY_CLASS,
+                OpenProcess, PROCESS_SET_INFORMATION, PROCESS_SUSPEND_RESUME, SetPriorityClass,
+            },
         },
     },
     core::s,
 };
 
+use crate::conversion::args::{
+    additional_output_path, output_variant_config, resolve_output_collision, resolve_output_path,
+};
 use crate::conversion::error::ConversionError;
-use crate::conversion::types::{ConversionTask, DEFAULT_MAX_CONCURRENCY};
+use crate::conversion::pipeline::{
+    collect_job_outputs, is_ready, output_source, resolve_step_input, step_output, take_dependents,
+};
+use crate::conversion::scheduler::{
+    add_cost, fits_budget, minutes_until_window_change, parse_clock, task_cost, window_is_open,
+};
+use crate::conversion::store::{JobStore, PersistedQueue};
+use crate::conversion::types::{
+    ConversionTask, DEFAULT_MAX_CONCURRENCY, ProcessPriority, QueuePosition, QueueSchedule,
+    QueueState, RetryPolicy, SchedulerConfig, SchedulerMode, StepOutput, TaskCost, TaskState,
+    TaskStatus,
+};
+use crate::conversion::utils::{local_minutes_of_day, task_temp_dir};
 use crate::conversion::worker::run_ffmpeg_worker;
 
 pub enum ManagerMessage {
     Enqueue(ConversionTask),
+    /// Every step of a pipeline job, in dependency order.
+    EnqueueJob(
+        Vec<ConversionTask>,
+        oneshot::Sender<Result<(), ConversionError>>,
+    ),
     ConcurrencyUpdated,
-    TaskStarted(String, u32),
+    QueueResumed,
+    ScheduleTick,
+    ScheduleUpdated,
+    TaskProgress(String, f64),
     TaskCompleted(String),
     TaskError(String, ConversionError),
+    TaskCancelled(String),
+    RetryDue(String),
+    ListInterrupted(oneshot::Sender<Vec<ConversionTask>>),
+    RequeueInterrupted(Option<Vec<String>>),
+    GetQueueState(oneshot::Sender<QueueState>),
+    MoveTask(
+        String,
+        QueuePosition,
+        oneshot::Sender<Result<(), ConversionError>>,
+    ),
+    SetPriority(String, i32, oneshot::Sender<Result<(), ConversionError>>),
+    RemoveTask(String, oneshot::Sender<Result<(), ConversionError>>),
 }
 
 pub struct ConversionManager {
     pub(crate) sender: mpsc::Sender<ManagerMessage>,
     max_concurrency: Arc<AtomicUsize>,
-    active_tasks: Arc<Mutex<HashMap<String, u32>>>,
+    queue_paused: Arc<AtomicBool>,
+    active_tasks: Arc<Mutex<HashMap<String, Vec<u32>>>>,
     cancelled_tasks: Arc<Mutex<HashSet<String>>>,
+    paused_tasks: Arc<Mutex<HashSet<String>>>,
+    stop_signals: Arc<Mutex<HashMap<String, watch::Sender<bool>>>>,
+    retry_policy: Arc<Mutex<RetryPolicy>>,
+    scheduler: Arc<Mutex<SchedulerConfig>>,
+    schedule: Arc<Mutex<QueueSchedule>>,
+    schedule_held: Arc<Mutex<HashSet<String>>>,
+}
+
+struct RunningTask {
+    task: ConversionTask,
+    output_path: String,
+    /// Claimed paths of the additional outputs, reserved like `output_path`.
+    additional_paths: Vec<String>,
+    started_at: u64,
+    progress: f64,
+    cost: TaskCost,
 }
 
+/// A failed task waiting out its backoff before it rejoins the queue.
+struct PendingRetry {
+    task: ConversionTask,
+    error: String,
+}
+
+/// Worker-side view of the manager's process bookkeeping for one running task.
+/// Every sidecar is registered as soon as it spawns, so cancel and pause reach
+/// each stage of a multi-process pipeline, including one that starts while the
+/// request is in flight.
+pub(crate) struct TaskProcesses {
+    id: String,
+    active_tasks: Arc<Mutex<HashMap<String, Vec<u32>>>>,
+    cancelled_tasks: Arc<Mutex<HashSet<String>>>,
+    paused_tasks: Arc<Mutex<HashSet<String>>>,
+    priority: ProcessPriority,
+}
+
+impl TaskProcesses {
+    pub(crate) fn new(
+        id: &str,
+        active_tasks: &Arc<Mutex<HashMap<String, Vec<u32>>>>,
+        cancelled_tasks: &Arc<Mutex<HashSet<String>>>,
+        paused_tasks: &Arc<Mutex<HashSet<String>>>,
+    ) -> Self {
+        Self {
+            id: id.to_string(),
+            active_tasks: Arc::clone(active_tasks),
+            cancelled_tasks: Arc::clone(cancelled_tasks),
+            paused_tasks: Arc::clone(paused_tasks),
+            priority: ProcessPriority::Normal,
+        }
+    }
+
+    /// Priority applied to every sidecar the task spawns.
+    pub(crate) fn with_priority(mut self, priority: ProcessPriority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub(crate) fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub(crate) fn priority(&self) -> ProcessPriority {
+        self.priority
+    }
+
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.cancelled_tasks.lock().unwrap().contains(&self.id)
+    }
+
+    /// Records a freshly spawned sidecar. A task cancelled in the meantime gets
+    /// its process killed and an error back; a paused one has it suspended.
+    pub(crate) fn register(&self, pid: u32) -> Result<(), ConversionError> {
+        let mut tasks = self.active_tasks.lock().unwrap();
+        if self.is_cancelled() {
+            if pid > 0 {
+                let _ = ConversionManager::terminate_process(pid);
+            }
+            return Err(ConversionError::Cancelled(self.id.clone()));
+        }
+        if let Some(pids) = tasks.get_mut(&self.id) {
+            pids.push(pid);
+        }
+        if self.paused_tasks.lock().unwrap().contains(&self.id) {
+            suspend_process(pid)?;
+        }
+        Ok(())
+    }
+
+    /// Forgets a sidecar that has exited, so its pid is never signalled again.
+    pub(crate) fn unregister(&self, pid: u32) {
+        let mut tasks = self.active_tasks.lock().unwrap();
+        if let Some(pids) = tasks.get_mut(&self.id) {
+            pids.retain(|p| *p != pid);
+        }
+    }
+}
+
+/// State owned by the manager's message loop. Every change to the queue or the
+/// running set is written to the job store so it survives a restart.
+struct ManagerLoop {
+    app: AppHandle,
+    tx: mpsc::Sender<ManagerMessage>,
+    store: JobStore,
+    queue: VecDeque<ConversionTask>,
+    queued_ids: HashSet<String>,
+    running_tasks: HashMap<String, RunningTask>,
+    retrying_tasks: HashMap<String, PendingRetry>,
+    /// Pipeline steps whose dependencies have not all completed.
+    blocked_tasks: Vec<ConversionTask>,
+    step_outputs: HashMap<String, StepOutput>,
+    interrupted_tasks: Vec<ConversionTask>,
+    finished_tasks: Vec<TaskState>,
+    max_concurrency: Arc<AtomicUsize>,
+    queue_paused: Arc<AtomicBool>,
+    active_tasks: Arc<Mutex<HashMap<String, Vec<u32>>>>,
+    cancelled_tasks: Arc<Mutex<HashSet<String>>>,
+    paused_tasks: Arc<Mutex<HashSet<String>>>,
+    stop_signals: Arc<Mutex<HashMap<String, watch::Sender<bool>>>>,
+    retry_policy: Arc<Mutex<RetryPolicy>>,
+    scheduler: Arc<Mutex<SchedulerConfig>>,
+    schedule: Arc<Mutex<QueueSchedule>>,
+    window_open: bool,
+    /// Running tasks suspended by the schedule, resumed when the window opens.
+    /// A task leaves the set once the user pauses or resumes it.
+    schedule_held: Arc<Mutex<HashSet<String>>>,
+}
+
+/// How often the manager checks whether the schedule window opened or closed.
+const SCHEDULE_TICK: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Upper bound for a single retry backoff, however many attempts came before.
+const MAX_RETRY_BACKOFF_MS: u64 = 60 * 60 * 1000;
+
 impl ConversionManager {
     pub fn new(app: AppHandle) -> Self {
         let (tx, mut rx) = mpsc::channel(32);
-        let tx_clone = tx.clone();
         let max_concurrency = Arc::new(AtomicUsize::new(DEFAULT_MAX_CONCURRENCY));
-        let limiter = Arc::clone(&max_concurrency);
+        let queue_paused = Arc::new(AtomicBool::new(false));
         let active_tasks = Arc::new(Mutex::new(HashMap::new()));
-        let active_tasks_loop = Arc::clone(&active_tasks);
         let cancelled_tasks = Arc::new(Mutex::new(HashSet::new()));
-        let cancelled_tasks_loop = Arc::clone(&cancelled_tasks);
-
-        tauri::async_runtime::spawn(async move {
-            let mut queue: VecDeque<ConversionTask> = VecDeque::new();
-            let mut queued_ids: HashSet<String> = HashSet::new();
-            let mut running_tasks: HashMap<String, ()> = HashMap::new();
+        let paused_tasks = Arc::new(Mutex::new(HashSet::new()));
+        let stop_signals = Arc::new(Mutex::new(HashMap::new()));
+        let retry_policy = Arc::new(Mutex::new(RetryPolicy::default()));
+        let scheduler = Arc::new(Mutex::new(SchedulerConfig::default()));
+        let schedule_held = Arc::new(Mutex::new(HashSet::new()));
 
-            while let Some(msg) = rx.recv().await {
-                match msg {
-                    ManagerMessage::Enqueue(task) => {
-                        {
-                            let mut cancelled = cancelled_tasks_loop.lock().unwrap();
-                            cancelled.remove(&task.id);
-                        }
-
-                        if running_tasks.contains_key(&task.id) || queued_ids.contains(&task.id) {
-                            continue;
-                        }
-
-                        queued_ids.insert(task.id.clone());
-                        queue.push_back(task);
-                        ConversionManager::process_queue(
-                            &app,
-                            &tx_clone,
-                            &mut queue,
-                            &mut queued_ids,
-                            &mut running_tasks,
-                            Arc::clone(&limiter),
-                            Arc::clone(&cancelled_tasks_loop),
-                        )
-                        .await;
-                    }
-                    ManagerMessage::ConcurrencyUpdated => {
-                        ConversionManager::process_queue(
-                            &app,
-                            &tx_clone,
-                            &mut queue,
-                            &mut queued_ids,
-                            &mut running_tasks,
-                            Arc::clone(&limiter),
-                            Arc::clone(&cancelled_tasks_loop),
-                        )
-                        .await;
-                    }
-                    ManagerMessage::TaskStarted(id, pid) => {
-                        let is_cancelled = {
-                            let cancelled = cancelled_tasks_loop.lock().unwrap();
-                            cancelled.contains(&id)
-                        };
+        let store = JobStore::new(app.path().app_data_dir().ok());
+        let persisted = store.load();
+        let schedule = Arc::new(Mutex::new(persisted.schedule.clone()));
+        let window_open = window_is_open(&persisted.schedule, local_minutes_of_day());
+        let blocked_tasks = persisted.blocked.clone();
+        let step_outputs = persisted.step_outputs.clone();
+        let (restored_queue, interrupted_tasks) = persisted.into_restored();
 
-                        if is_cancelled {
-                            if pid > 0 {
-                                let _ = ConversionManager::terminate_process(pid);
-                            }
-                            running_tasks.remove(&id);
-                            {
-                                let mut tasks = active_tasks_loop.lock().unwrap();
-                                tasks.remove(&id);
-                            }
-                            ConversionManager::process_queue(
-                                &app,
-                                &tx_clone,
-                                &mut queue,
-                                &mut queued_ids,
-                                &mut running_tasks,
-                                Arc::clone(&limiter),
-                                Arc::clone(&cancelled_tasks_loop),
-                            )
-                            .await;
-                            continue;
-                        }
+        let mut state = ManagerLoop {
+            app,
+            tx: tx.clone(),
+            store,
+            queue: VecDeque::new(),
+            queued_ids: HashSet::new(),
+            running_tasks: HashMap::new(),
+            retrying_tasks: HashMap::new(),
+            blocked_tasks,
+            step_outputs,
+            interrupted_tasks,
+            finished_tasks: Vec::new(),
+            max_concurrency: Arc::clone(&max_concurrency),
+            queue_paused: Arc::clone(&queue_paused),
+            active_tasks: Arc::clone(&active_tasks),
+            cancelled_tasks: Arc::clone(&cancelled_tasks),
+            paused_tasks: Arc::clone(&paused_tasks),
+            stop_signals: Arc::clone(&stop_signals),
+            retry_policy: Arc::clone(&retry_policy),
+            scheduler: Arc::clone(&scheduler),
+            schedule: Arc::clone(&schedule),
+            window_open,
+            schedule_held: Arc::clone(&schedule_held),
+        };
 
-                        let mut tasks = active_tasks_loop.lock().unwrap();
-                        tasks.insert(id, pid);
-                    }
-                    ManagerMessage::TaskCompleted(id) => {
-                        running_tasks.remove(&id);
-                        {
-                            let mut cancelled = cancelled_tasks_loop.lock().unwrap();
-                            cancelled.remove(&id);
-                        }
-                        {
-                            let mut tasks = active_tasks_loop.lock().unwrap();
-                            tasks.remove(&id);
-                        }
-
-                        ConversionManager::process_queue(
-                            &app,
-                            &tx_clone,
-                            &mut queue,
-                            &mut queued_ids,
-                            &mut running_tasks,
-                            Arc::clone(&limiter),
-                            Arc::clone(&cancelled_tasks_loop),
-                        )
-                        .await;
-                    }
-                    ManagerMessage::TaskError(id, err) => {
-                        eprintln!("Task {} failed: {}", id, err);
+        for task in restored_queue {
+            if state.queued_ids.insert(task.id.clone()) {
+                state.queue.push_back(task);
+            }
+        }
 
-                        let _ = app.emit(
-                            "conversion-log",
-                            LogPayload {
-                                id: id.clone(),
-                                line: format!("[ERROR] {}", err),
-                            },
-                        );
+        let tick_tx = tx.clone();
+        tauri::async_runtime::spawn(async move {
+            loop {
+                tokio::time::sleep(SCHEDULE_TICK).await;
+                if tick_tx.send(ManagerMessage::ScheduleTick).await.is_err() {
+                    break;
+                }
+            }
+        });
 
-                        let _ = app.emit(
-                            "conversion-error",
-                            ErrorPayload {
-                                id: id.clone(),
-                                error: err.to_string(),
-                            },
-                        );
+        tauri::async_runtime::spawn(async move {
+            if !state.window_open {
+                state.emit_schedule();
+            }
+            state.release_ready_steps();
+            state.process_queue().await;
+            state.persist();
 
-                        running_tasks.remove(&id);
-                        {
-                            let mut cancelled = cancelled_tasks_loop.lock().unwrap();
-                            cancelled.remove(&id);
-                        }
-                        {
-                            let mut tasks = active_tasks_loop.lock().unwrap();
-                            tasks.remove(&id);
-                        }
-
-                        ConversionManager::process_queue(
-                            &app,
-                            &tx_clone,
-                            &mut queue,
-                            &mut queued_ids,
-                            &mut running_tasks,
-                            Arc::clone(&limiter),
-                            Arc::clone(&cancelled_tasks_loop),
-                        )
-                        .await;
-                    }
-                }
+            while let Some(msg) = rx.recv().await {
+                state.handle(msg).await;
             }
         });
 
         Self {
             sender: tx,
             max_concurrency,
+            queue_paused,
             active_tasks,
             cancelled_tasks,
-        }
-    }
-
-    async fn process_queue(
-        app: &AppHandle,
-        tx: &mpsc::Sender<ManagerMessage>,
-        queue: &mut VecDeque<ConversionTask>,
-        queued_ids: &mut HashSet<String>,
-        running_tasks: &mut HashMap<String, ()>,
-        max_concurrency: Arc<AtomicUsize>,
-        cancelled_tasks: Arc<Mutex<HashSet<String>>>,
-    ) {
-        let limit = max_concurrency.load(Ordering::SeqCst).max(1);
-
-        while running_tasks.len() < limit {
-            if let Some(task) = queue.pop_front() {
-                queued_ids.remove(&task.id);
-                let is_cancelled = {
-                    let mut cancelled = cancelled_tasks.lock().unwrap();
-                    cancelled.remove(&task.id)
-                };
-                if is_cancelled {
-                    continue;
-                }
-
-                running_tasks.insert(task.id.clone(), ());
-
-                let app_clone = app.clone();
-                let tx_worker = tx.clone();
-                let task_clone = task.clone();
-
-                tauri::async_runtime::spawn(async move {
-                    if let Err(e) =
-                        run_ffmpeg_worker(app_clone, tx_worker.clone(), task_clone.clone()).await
-                    {
-                        let _ = tx_worker
-                            .send(ManagerMessage::TaskError(task_clone.id, e))
-                            .await;
-                    } else {
-                        let _ = tx_worker
-                            .send(ManagerMessage::TaskCompleted(task_clone.id))
-                            .await;
-                    }
-                });
-            } else {
-                break;
-            }
+            paused_tasks,
+            stop_signals,
+            retry_policy,
+            scheduler,
+            schedule,
+            schedule_held,
         }
     }
 
@@ -265,54 +323,124 @@ impl ConversionManager {
         Ok(())
     }
 
-    pub fn pause_task(&self, id: &str) -> Result<(), ConversionError> {
-        let tasks = self.active_tasks.lock().unwrap();
-        if let Some(&pid) = tasks.get(id) {
-            if pid == 0 {
-                return Err(ConversionError::TaskNotFound(id.to_string()));
-            }
+    pub fn current_retry_policy(&self) -> RetryPolicy {
+        self.retry_policy.lock().unwrap().clone()
+    }
 
-            #[cfg(unix)]
-            unsafe {
-                if libc::kill(pid as libc::pid_t, libc::SIGSTOP) != 0 {
-                    return Err(ConversionError::Shell("Failed to send SIGSTOP".to_string()));
-                }
-            }
+    pub fn update_retry_policy(&self, policy: RetryPolicy) -> Result<(), ConversionError> {
+        validate_retry_policy(&policy)?;
+        *self.retry_policy.lock().unwrap() = policy;
+        Ok(())
+    }
 
-            #[cfg(windows)]
-            unsafe {
-                windows_suspend_resume(pid, true)?;
-            }
+    pub fn current_scheduler(&self) -> SchedulerConfig {
+        self.scheduler.lock().unwrap().clone()
+    }
 
-            Ok(())
-        } else {
-            Err(ConversionError::TaskNotFound(id.to_string()))
+    pub fn update_scheduler(&self, scheduler: SchedulerConfig) -> Result<(), ConversionError> {
+        validate_scheduler_config(&scheduler)?;
+        *self.scheduler.lock().unwrap() = scheduler;
+        let tx = self.sender.clone();
+        tauri::async_runtime::spawn(async move {
+            let _ = tx.send(ManagerMessage::ConcurrencyUpdated).await;
+        });
+        Ok(())
+    }
+
+    pub fn current_schedule(&self) -> QueueSchedule {
+        self.schedule.lock().unwrap().clone()
+    }
+
+    pub fn update_schedule(&self, schedule: QueueSchedule) -> Result<(), ConversionError> {
+        validate_queue_schedule(&schedule)?;
+        *self.schedule.lock().unwrap() = schedule;
+        let tx = self.sender.clone();
+        tauri::async_runtime::spawn(async move {
+            let _ = tx.send(ManagerMessage::ScheduleUpdated).await;
+        });
+        Ok(())
+    }
+
+    pub fn pause_task(&self, id: &str) -> Result<(), ConversionError> {
+        let tasks = self.active_tasks.lock().unwrap();
+        let pids = tasks
+            .get(id)
+            .ok_or_else(|| ConversionError::TaskNotFound(id.to_string()))?;
+        for &pid in pids {
+            suspend_process(pid)?;
         }
+
+        // Recorded under the same lock `TaskProcesses::register` takes, so a
+        // stage that spawns next is suspended as soon as it registers.
+        self.paused_tasks.lock().unwrap().insert(id.to_string());
+        // The pause is the user's now; opening the window must not undo it.
+        self.schedule_held.lock().unwrap().remove(id);
+        Ok(())
     }
 
     pub fn resume_task(&self, id: &str) -> Result<(), ConversionError> {
         let tasks = self.active_tasks.lock().unwrap();
-        if let Some(&pid) = tasks.get(id) {
-            if pid == 0 {
-                return Err(ConversionError::TaskNotFound(id.to_string()));
-            }
+        let pids = tasks
+            .get(id)
+            .ok_or_else(|| ConversionError::TaskNotFound(id.to_string()))?;
+        for &pid in pids {
+            resume_process(pid)?;
+        }
 
-            #[cfg(unix)]
-            unsafe {
-                if libc::kill(pid as libc::pid_t, libc::SIGCONT) != 0 {
-                    return Err(ConversionError::Shell("Failed to send SIGCONT".to_string()));
-                }
-            }
+        self.paused_tasks.lock().unwrap().remove(id);
+        self.schedule_held.lock().unwrap().remove(id);
+        Ok(())
+    }
 
-            #[cfg(windows)]
-            unsafe {
-                windows_suspend_resume(pid, false)?;
-            }
+    /// Suspends every running task and holds the queue, so nothing new starts
+    /// until `resume_all`. Tasks the schedule holds stay held, so `resume_all`
+    /// cannot run them outside the window.
+    pub fn pause_all(&self) -> Result<(), ConversionError> {
+        self.queue_paused.store(true, Ordering::SeqCst);
 
-            Ok(())
-        } else {
-            Err(ConversionError::TaskNotFound(id.to_string()))
+        let tasks = self.active_tasks.lock().unwrap();
+        let mut paused = self.paused_tasks.lock().unwrap();
+        suspend_tasks(&tasks, &mut paused)
+    }
+
+    /// Resumes every paused task, including ones paused individually, and lets
+    /// the queue start new tasks again. Tasks the schedule suspended stay held
+    /// until the window opens.
+    pub fn resume_all(&self) -> Result<(), ConversionError> {
+        let result = {
+            let tasks = self.active_tasks.lock().unwrap();
+            let mut paused = self.paused_tasks.lock().unwrap();
+            let held = self.schedule_held.lock().unwrap();
+            resume_paused_tasks(&tasks, &mut paused, &held)
+        };
+
+        self.queue_paused.store(false, Ordering::SeqCst);
+        let tx = self.sender.clone();
+        tauri::async_runtime::spawn(async move {
+            let _ = tx.send(ManagerMessage::QueueResumed).await;
+        });
+        result
+    }
+
+    /// Asks ffmpeg to end the encode where it is and finalize the container,
+    /// so the output written so far stays playable.
+    pub fn stop_task(&self, id: &str) -> Result<(), ConversionError> {
+        {
+            let signals = self.stop_signals.lock().unwrap();
+            let signal = signals
+                .get(id)
+                .ok_or_else(|| ConversionError::TaskNotFound(id.to_string()))?;
+            signal.send(true).map_err(|_| {
+                ConversionError::InvalidInput(format!("Task {} cannot be stopped early", id))
+            })?;
         }
+
+        // A suspended ffmpeg only reads the stop request once it runs again.
+        let is_paused = self.paused_tasks.lock().unwrap().contains(id);
+        if is_paused {
+            self.resume_task(id)?;
+        }
+        Ok(())
     }
 
     pub fn cancel_task(&self, id: &str) -> Result<(), ConversionError> {
@@ -321,23 +449,36 @@ impl ConversionManager {
             cancelled.insert(id.to_string());
         }
 
-        let tasks = self.active_tasks.lock().unwrap();
-        if let Some(&pid) = tasks.get(id) {
-            if pid > 0 {
-                ConversionManager::terminate_process(pid)?;
+        let tx = self.sender.clone();
+        let cancelled_id = id.to_string();
+        tauri::async_runtime::spawn(async move {
+            let _ = tx.send(ManagerMessage::TaskCancelled(cancelled_id)).await;
+        });
+
+        // Any sidecar registered after this point sees the cancel and kills itself.
+        let pids = self
+            .active_tasks
+            .lock()
+            .unwrap()
+            .get(id)
+            .cloned()
+            .unwrap_or_default();
+        let mut result = Ok(());
+        for pid in pids.into_iter().filter(|pid| *pid > 0) {
+            if let Err(e) = ConversionManager::terminate_process(pid) {
+                result = Err(e);
             }
-            ConversionManager::cleanup_temp_upscale_dir(id);
-            Ok(())
-        } else {
-            ConversionManager::cleanup_temp_upscale_dir(id);
-            Ok(())
         }
+        ConversionManager::cleanup_task_temp_dirs(id);
+        result
     }
 
-    fn cleanup_temp_upscale_dir(id: &str) {
-        let temp_dir = std::env::temp_dir().join(format!("frame_upscale_{}", id));
-        if temp_dir.exists() {
-            let _ = std::fs::remove_dir_all(&temp_dir);
+    fn cleanup_task_temp_dirs(id: &str) {
+        for kind in ["upscale", "passlog", "concat"] {
+            let temp_dir = task_temp_dir(kind, id);
+            if temp_dir.exists() {
+                let _ = std::fs::remove_dir_all(&temp_dir);
+            }
         }
     }
 
@@ -373,6 +514,1136 @@ impl ConversionManager {
     }
 }
 
+impl ManagerLoop {
+    async fn handle(&mut self, msg: ManagerMessage) {
+        match msg {
+            ManagerMessage::Enqueue(task) => {
+                if !self.enqueue(task) {
+                    return;
+                }
+                if !self.window_open {
+                    self.emit_schedule();
+                }
+                self.process_queue().await;
+            }
+            ManagerMessage::EnqueueJob(tasks, reply) => {
+                // A dropped step would leave its dependents blocked forever,
+                // so the whole job is refused instead.
+                if let Some(task) = tasks
+                    .iter()
+                    .find(|task| self.pending_tasks().any(|pending| pending.id == task.id))
+                {
+                    let _ = reply.send(Err(ConversionError::InvalidInput(format!(
+                        "Pipeline step {} has the id of a task that is still pending",
+                        task.id
+                    ))));
+                    return;
+                }
+                let _ = reply.send(Ok(()));
+                for task in tasks {
+                    self.enqueue(task);
+                }
+                self.release_ready_steps();
+                if !self.window_open {
+                    self.emit_schedule();
+                }
+                self.process_queue().await;
+            }
+            ManagerMessage::ConcurrencyUpdated | ManagerMessage::QueueResumed => {
+                self.process_queue().await;
+            }
+            ManagerMessage::ScheduleTick => {
+                if !self.refresh_window() {
+                    return;
+                }
+                self.emit_schedule();
+                self.process_queue().await;
+            }
+            ManagerMessage::ScheduleUpdated => {
+                self.refresh_window();
+                self.emit_schedule();
+                self.process_queue().await;
+            }
+            ManagerMessage::TaskProgress(id, progress) => {
+                if let Some(running) = self.running_tasks.get_mut(&id) {
+                    running.progress = progress;
+                }
+                return;
+            }
+            ManagerMessage::TaskCompleted(id) => {
+                let status = if self.was_stopped(&id) {
+                    TaskStatus::Stopped
+                } else {
+                    TaskStatus::Completed
+                };
+                self.finish_task(&id, status, None);
+                self.process_queue().await;
+            }
+            ManagerMessage::TaskError(id, err) => {
+                let was_cancelled = self.cancelled_tasks.lock().unwrap().contains(&id);
+                if was_cancelled || self.was_stopped(&id) {
+                    self.report_error(&id, &err);
+                    self.finish_task(&id, TaskStatus::Cancelled, None);
+                } else if !self.schedule_retry(&id, &err) {
+                    self.report_error(&id, &err);
+                    self.finish_task(&id, TaskStatus::Failed, Some(err.to_string()));
+                }
+                self.process_queue().await;
+            }
+            ManagerMessage::TaskCancelled(id) => {
+                if self.queued_ids.remove(&id) {
+                    if let Some(task) = self
+                        .queue
+                        .iter()
+                        .position(|t| t.id == id)
+                        .and_then(|pos| self.queue.remove(pos))
+                    {
+                        self.record_finished(&task, TaskStatus::Cancelled, None, 0.0, None);
+                    }
+                    let mut cancelled = self.cancelled_tasks.lock().unwrap();
+                    cancelled.remove(&id);
+                }
+                if let Some(pending) = self.retrying_tasks.remove(&id) {
+                    self.record_finished(&pending.task, TaskStatus::Cancelled, None, 0.0, None);
+                    let mut cancelled = self.cancelled_tasks.lock().unwrap();
+                    cancelled.remove(&id);
+                }
+                if let Some(pos) = self.blocked_tasks.iter().position(|t| t.id == id) {
+                    let task = self.blocked_tasks.remove(pos);
+                    self.record_finished(&task, TaskStatus::Cancelled, None, 0.0, None);
+                    let mut cancelled = self.cancelled_tasks.lock().unwrap();
+                    cancelled.remove(&id);
+                }
+                if let Some(pos) = self.interrupted_tasks.iter().position(|t| t.id == id) {
+                    let task = self.interrupted_tasks.remove(pos);
+                    self.record_finished(&task, TaskStatus::Cancelled, None, 0.0, None);
+                    let mut cancelled = self.cancelled_tasks.lock().unwrap();
+                    cancelled.remove(&id);
+                }
+            }
+            ManagerMessage::RetryDue(id) => {
+                if let Some(pending) = self.retrying_tasks.remove(&id) {
+                    self.queued_ids.insert(id);
+                    self.insert_by_priority(pending.task);
+                    self.process_queue().await;
+                }
+            }
+            ManagerMessage::ListInterrupted(reply) => {
+                let _ = reply.send(self.interrupted_tasks.clone());
+                return;
+            }
+            ManagerMessage::GetQueueState(reply) => {
+                let _ = reply.send(self.queue_state());
+                return;
+            }
+            ManagerMessage::MoveTask(id, position, reply) => {
+                // Blocked steps join the queue by priority once released.
+                let result = if self.blocked_tasks.iter().any(|t| t.id == id) {
+                    Err(ConversionError::InvalidInput(format!(
+                        "Task {} is waiting on earlier pipeline steps and cannot be moved",
+                        id
+                    )))
+                } else {
+                    self.take_queued(&id).map(|task| {
+                        let index = match position {
+                            QueuePosition::Top => 0,
+                            QueuePosition::Bottom => self.queue.len(),
+                            QueuePosition::Index(index) => index.min(self.queue.len()),
+                        };
+                        self.queue.insert(index, task);
+                    })
+                };
+                let _ = reply.send(result);
+            }
+            ManagerMessage::SetPriority(id, priority, reply) => {
+                let result = match self.blocked_tasks.iter_mut().find(|t| t.id == id) {
+                    Some(task) => {
+                        task.priority = priority;
+                        Ok(())
+                    }
+                    None => self.take_queued(&id).map(|mut task| {
+                        task.priority = priority;
+                        self.insert_by_priority(task);
+                    }),
+                };
+                let _ = reply.send(result);
+            }
+            ManagerMessage::RemoveTask(id, reply) => {
+                let result = self.take_queued(&id).map(|task| {
+                    self.queued_ids.remove(&id);
+                    if let Some(job) = &task.job {
+                        self.abandon_dependents(&id, format!("Pipeline step {} was removed", id));
+                        self.collect_intermediates(&job.job_id);
+                    }
+                });
+                let _ = reply.send(result);
+            }
+            ManagerMessage::RequeueInterrupted(ids) => {
+                let (requeue, keep): (Vec<_>, Vec<_>) = std::mem::take(&mut self.interrupted_tasks)
+                    .into_iter()
+                    .partition(|t| ids.as_ref().is_none_or(|ids| ids.contains(&t.id)));
+                self.interrupted_tasks = keep;
+
+                for task in requeue {
+                    if self.running_tasks.contains_key(&task.id)
+                        || self.retrying_tasks.contains_key(&task.id)
+                        || !self.queued_ids.insert(task.id.clone())
+                    {
+                        continue;
+                    }
+                    {
+                        let mut cancelled = self.cancelled_tasks.lock().unwrap();
+                        cancelled.remove(&task.id);
+                    }
+                    self.insert_by_priority(task);
+                }
+                self.process_queue().await;
+            }
+        }
+
+        self.persist();
+    }
+
+    fn report_error(&self, id: &str, err: &ConversionError) {
+        eprintln!("Task {} failed: {}", id, err);
+
+        let _ = self.app.emit(
+            "conversion-log",
+            LogPayload {
+                id: id.to_string(),
+                line: format!("[ERROR] {}", err),
+            },
+        );
+
+        let _ = self.app.emit(
+            "conversion-error",
+            ErrorPayload {
+                id: id.to_string(),
+                error: err.to_string(),
+                kind: err.kind().to_string(),
+                excerpt: err.excerpt().map(str::to_string),
+            },
+        );
+    }
+
+    /// Takes a failed task out of the running set and queues it again after the
+    /// backoff, if its retry policy allows another attempt for this error.
+    fn schedule_retry(&mut self, id: &str, err: &ConversionError) -> bool {
+        let Some(running) = self.running_tasks.get(id) else {
+            return false;
+        };
+        let policy = running
+            .task
+            .retry_policy
+            .clone()
+            .unwrap_or_else(|| self.retry_policy.lock().unwrap().clone());
+        let attempts = running.task.attempts + 1;
+        let Some(delay_ms) = retry_delay_ms(&policy, attempts, err.kind()) else {
+            return false;
+        };
+
+        let Some(running) = self.running_tasks.remove(id) else {
+            return false;
+        };
+        self.release_task(id);
+        let mut task = running.task;
+        task.attempts = attempts;
+
+        eprintln!(
+            "Task {} failed (attempt {}/{}), retrying in {} ms: {}",
+            id, attempts, policy.max_attempts, delay_ms, err
+        );
+        let _ = self.app.emit(
+            "conversion-log",
+            LogPayload {
+                id: id.to_string(),
+                line: format!(
+                    "[RETRY] Attempt {}/{} failed, retrying in {:.1}s: {}",
+                    attempts,
+                    policy.max_attempts,
+                    delay_ms as f64 / 1000.0,
+                    err
+                ),
+            },
+        );
+        let _ = self.app.emit(
+            "conversion-retry",
+            RetryPayload {
+                id: id.to_string(),
+                attempt: attempts + 1,
+                max_attempts: policy.max_attempts,
+                delay_ms,
+                error: err.to_string(),
+                kind: err.kind().to_string(),
+            },
+        );
+
+        self.retrying_tasks.insert(
+            id.to_string(),
+            PendingRetry {
+                task,
+                error: err.to_string(),
+            },
+        );
+        let tx = self.tx.clone();
+        let id = id.to_string();
+        tauri::async_runtime::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(delay_ms)).await;
+            let _ = tx.send(ManagerMessage::RetryDue(id)).await;
+        });
+        true
+    }
+
+    /// Resolves the task's output path against files on disk and the outputs of
+    /// running tasks. Skipped and failed tasks are recorded here and never spawned.
+    fn claim_output_path(
+        &mut self,
+        task: &ConversionTask,
+    ) -> Option<(String, Vec<Option<String>>)> {
+        let output_path =
+            resolve_output_path(output_source(task), &task.config, task.output_name.clone());
+        let mut reserved: HashSet<String> = self
+            .running_tasks
+            .values()
+            .flat_map(|running| {
+                std::iter::once(&running.output_path).chain(&running.additional_paths)
+            })
+            .cloned()
+            .collect();
+        let exists = |path: &str| std::path::Path::new(path).exists();
+
+        let claimed =
+            resolve_output_collision(&output_path, task.collision_policy, &reserved, exists)
+                .and_then(|path| match path {
+                    Some(path) => claim_additional_outputs(task, &path, &mut reserved, exists)
+                        .map(|additional| Some((path, additional))),
+                    None => Ok(None),
+                });
+        match claimed {
+            Ok(Some((path, additional))) => {
+                let mut paths = Vec::with_capacity(additional.len());
+                for (requested, claimed) in additional {
+                    if claimed.is_none() {
+                        let _ = self.app.emit(
+                            "conversion-log",
+                            LogPayload {
+                                id: task.id.clone(),
+                                line: format!("[SKIP] {} already exists", requested),
+                            },
+                        );
+                    }
+                    paths.push(claimed);
+                }
+                Some((path, paths))
+            }
+            Ok(None) => {
+                // The existing file stands in for the step's output, but it
+                // is the user's, so it is never collected as an intermediate.
+                self.record_step_output(task, &output_path, false);
+                let _ = self.app.emit(
+                    "conversion-log",
+                    LogPayload {
+                        id: task.id.clone(),
+                        line: format!("Skipped: {} already exists", output_path),
+                    },
+                );
+                let _ = self.app.emit(
+                    "conversion-completed",
+                    CompletedPayload {
+                        id: task.id.clone(),
+                        output_size: std::fs::metadata(&output_path).ok().map(|m| m.len()),
+                        output_path,
+                        target_size: None,
+                        skipped: true,
+                        verification: None,
+                        encoder_fallback: None,
+                        partial: false,
+                        encoded_duration: None,
+                        additional_outputs: Vec::new(),
+                    },
+                );
+                self.record_finished(task, TaskStatus::Skipped, None, 0.0, None);
+                None
+            }
+            Err(e) => {
+                self.report_error(&task.id, &e);
+                self.record_finished(task, TaskStatus::Failed, None, 0.0, Some(e.to_string()));
+                None
+            }
+        }
+    }
+
+    fn insert_by_priority(&mut self, task: ConversionTask) {
+        let index = priority_insert_index(&self.queue, task.priority);
+        self.queue.insert(index, task);
+    }
+
+    /// Detaches a pending task from the queue, or a step from the blocked
+    /// ones, while keeping its id reserved.
+    fn take_queued(&mut self, id: &str) -> Result<ConversionTask, ConversionError> {
+        let is_cancelled = self.cancelled_tasks.lock().unwrap().contains(id);
+        if is_cancelled {
+            return Err(ConversionError::TaskNotFound(id.to_string()));
+        }
+
+        if let Some(pos) = self.blocked_tasks.iter().position(|t| t.id == id) {
+            return Ok(self.blocked_tasks.remove(pos));
+        }
+        self.queue
+            .iter()
+            .position(|t| t.id == id)
+            .and_then(|pos| self.queue.remove(pos))
+            .ok_or_else(|| ConversionError::TaskNotFound(id.to_string()))
+    }
+
+    fn finish_task(&mut self, id: &str, status: TaskStatus, error: Option<String>) {
+        if let Some(running) = self.running_tasks.remove(id) {
+            let progress = if status == TaskStatus::Completed {
+                100.0
+            } else {
+                running.progress
+            };
+            if status == TaskStatus::Completed {
+                self.record_step_output(&running.task, &running.output_path, true);
+            }
+            self.record_finished(
+                &running.task,
+                status,
+                Some(running.started_at),
+                progress,
+                error,
+            );
+        }
+        self.release_task(id);
+    }
+
+    fn was_stopped(&self, id: &str) -> bool {
+        self.stop_signals
+            .lock()
+            .unwrap()
+            .get(id)
+            .is_some_and(|signal| *signal.borrow())
+    }
+
+    /// Forgets the per-task process state shared with `ConversionManager`.
+    fn release_task(&mut self, id: &str) {
+        {
+            let mut held = self.schedule_held.lock().unwrap();
+            held.remove(id);
+        }
+        {
+            let mut signals = self.stop_signals.lock().unwrap();
+            signals.remove(id);
+        }
+        {
+            let mut cancelled = self.cancelled_tasks.lock().unwrap();
+            cancelled.remove(id);
+        }
+        {
+            let mut tasks = self.active_tasks.lock().unwrap();
+            tasks.remove(id);
+        }
+        {
+            let mut paused = self.paused_tasks.lock().unwrap();
+            paused.remove(id);
+        }
+    }
+
+    fn record_finished(
+        &mut self,
+        task: &ConversionTask,
+        status: TaskStatus,
+        started_at: Option<u64>,
+        progress: f64,
+        error: Option<String>,
+    ) {
+        self.finished_tasks.retain(|t| t.id != task.id);
+        self.finished_tasks.push(TaskState {
+            started_at,
+            finished_at: Some(unix_millis()),
+            progress,
+            error,
+            ..task_state(task, status)
+        });
+
+        if let Some(job) = &task.job {
+            let job_id = job.job_id.clone();
+            self.emit_pipeline(&job_id, &task.id, status);
+            self.settle_step(task, status);
+            self.collect_intermediates(&job_id);
+        }
+    }
+
+    /// Adds a task to the queue, or parks it while its pipeline dependencies
+    /// are outstanding. Returns false for a task that is already known.
+    fn enqueue(&mut self, task: ConversionTask) -> bool {
+        {
+            let mut cancelled = self.cancelled_tasks.lock().unwrap();
+            cancelled.remove(&task.id);
+        }
+
+        if self.running_tasks.contains_key(&task.id)
+            || self.queued_ids.contains(&task.id)
+            || self.retrying_tasks.contains_key(&task.id)
+            || self.blocked_tasks.iter().any(|t| t.id == task.id)
+        {
+            return false;
+        }
+
+        self.interrupted_tasks.retain(|t| t.id != task.id);
+        self.finished_tasks.retain(|t| t.id != task.id);
+        if is_ready(&task, &self.step_outputs) {
+            self.queued_ids.insert(task.id.clone());
+            self.insert_by_priority(task);
+        } else {
+            self.blocked_tasks.push(task);
+        }
+        true
+    }
+
+    fn record_step_output(&mut self, task: &ConversionTask, output_path: &str, created: bool) {
+        if let Some(output) = step_output(task, output_path, created) {
+            self.step_outputs.insert(task.id.clone(), output);
+        }
+    }
+
+    /// Moves blocked steps whose dependencies have all completed to the
+    /// queue, reading their input from the step named in `input_from`.
+    fn release_ready_steps(&mut self) {
+        let (ready, blocked): (Vec<_>, Vec<_>) = std::mem::take(&mut self.blocked_tasks)
+            .into_iter()
+            .partition(|task| is_ready(task, &self.step_outputs));
+        self.blocked_tasks = blocked;
+
+        for mut task in ready {
+            resolve_step_input(&mut task, &self.step_outputs);
+            self.queued_ids.insert(task.id.clone());
+            self.insert_by_priority(task);
+        }
+    }
+
+    /// A step that finished without an output takes the steps waiting on it
+    /// down with it; one that produced an output may unblock others.
+    fn settle_step(&mut self, task: &ConversionTask, status: TaskStatus) {
+        if self.step_outputs.contains_key(&task.id) {
+            self.release_ready_steps();
+            return;
+        }
+
+        let reason = format!("Pipeline step {} ended as {:?}", task.id, status);
+        self.abandon_dependents(&task.id, reason);
+    }
+
+    /// Cancels every blocked step that can no longer run without `step_id`.
+    fn abandon_dependents(&mut self, step_id: &str, reason: String) {
+        for waiting in take_dependents(&mut self.blocked_tasks, step_id) {
+            let _ = self.app.emit(
+                "conversion-log",
+                LogPayload {
+                    id: waiting.id.clone(),
+                    line: format!("[PIPELINE] Not started: {}", reason),
+                },
+            );
+            self.record_finished(
+                &waiting,
+                TaskStatus::Cancelled,
+                None,
+                0.0,
+                Some(reason.clone()),
+            );
+        }
+    }
+
+    fn pending_tasks(&self) -> impl Iterator<Item = &ConversionTask> {
+        self.queue
+            .iter()
+            .chain(self.blocked_tasks.iter())
+            .chain(self.running_tasks.values().map(|running| &running.task))
+            .chain(self.retrying_tasks.values().map(|pending| &pending.task))
+            .chain(self.interrupted_tasks.iter())
+    }
+
+    /// Deletes intermediate outputs no pending step of `job_id` reads any
+    /// more, and forgets the job's outputs once none of its steps are left.
+    fn collect_intermediates(&mut self, job_id: &str) {
+        let pending: Vec<ConversionTask> = self
+            .pending_tasks()
+            .filter(|task| task.job.as_ref().is_some_and(|job| job.job_id == job_id))
+            .cloned()
+            .collect();
+        collect_job_outputs(
+            job_id,
+            &mut self.step_outputs,
+            &pending,
+            &task_temp_dir("pipeline", job_id),
+        );
+    }
+
+    fn emit_pipeline(&self, job_id: &str, step_id: &str, status: TaskStatus) {
+        let in_job = |job: Option<&str>| job == Some(job_id);
+        let pending = self
+            .pending_tasks()
+            .filter(|task| in_job(task.job.as_ref().map(|job| job.job_id.as_str())))
+            .count();
+        let finished: Vec<&TaskState> = self
+            .finished_tasks
+            .iter()
+            .filter(|state| in_job(state.job_id.as_deref()))
+            .collect();
+        let completed_steps = finished
+            .iter()
+            .filter(|state| matches!(state.status, TaskStatus::Completed | TaskStatus::Skipped))
+            .count();
+
+        let _ = self.app.emit(
+            "conversion-pipeline",
+            PipelinePayload {
+                job_id: job_id.to_string(),
+                step_id: step_id.to_string(),
+                status,
+                completed_steps,
+                total_steps: pending + finished.len(),
+            },
+        );
+    }
+
+    fn queue_state(&self) -> QueueState {
+        let active = self.active_tasks.lock().unwrap().clone();
+        let paused = self.paused_tasks.lock().unwrap().clone();
+        let cancelled = self.cancelled_tasks.lock().unwrap().clone();
+
+        let mut tasks: Vec<TaskState> = Vec::new();
+
+        let mut running: Vec<&RunningTask> = self.running_tasks.values().collect();
+        running.sort_by_key(|r| r.started_at);
+        for r in running {
+            let status = if paused.contains(&r.task.id) {
+                TaskStatus::Paused
+            } else {
+                TaskStatus::Running
+            };
+            tasks.push(TaskState {
+                pid: active
+                    .get(&r.task.id)
+                    .and_then(|pids| pids.last().copied())
+                    .filter(|pid| *pid > 0),
+                started_at: Some(r.started_at),
+                progress: r.progress,
+                ..task_state(&r.task, status)
+            });
+        }
+
+        for (position, task) in self
+            .queue
+            .iter()
+            .filter(|t| !cancelled.contains(&t.id))
+            .enumerate()
+        {
+            tasks.push(TaskState {
+                position: Some(position),
+                ..task_state(task, TaskStatus::Queued)
+            });
+        }
+
+        for task in &self.blocked_tasks {
+            tasks.push(task_state(task, TaskStatus::Blocked));
+        }
+
+        for pending in self.retrying_tasks.values() {
+            tasks.push(TaskState {
+                error: Some(pending.error.clone()),
+                ..task_state(&pending.task, TaskStatus::Retrying)
+            });
+        }
+
+        for task in &self.interrupted_tasks {
+            tasks.push(task_state(task, TaskStatus::Interrupted));
+        }
+
+        tasks.extend(self.finished_tasks.iter().cloned());
+
+        QueueState {
+            max_concurrency: self.max_concurrency.load(Ordering::SeqCst),
+            paused: self.queue_paused.load(Ordering::SeqCst),
+            scheduler: self.scheduler.lock().unwrap().clone(),
+            in_use: self.cost_in_use(),
+            schedule: self.schedule.lock().unwrap().clone(),
+            window_open: self.window_open,
+            tasks,
+        }
+    }
+
+    fn persist(&self) {
+        let cancelled = self.cancelled_tasks.lock().unwrap().clone();
+        let snapshot = PersistedQueue {
+            queued: self
+                .queue
+                .iter()
+                .chain(self.retrying_tasks.values().map(|pending| &pending.task))
+                .filter(|t| !cancelled.contains(&t.id))
+                .cloned()
+                .collect(),
+            running: self
+                .running_tasks
+                .values()
+                .filter(|r| !cancelled.contains(&r.task.id))
+                .map(|r| r.task.clone())
+                .collect(),
+            interrupted: self.interrupted_tasks.clone(),
+            blocked: self
+                .blocked_tasks
+                .iter()
+                .filter(|t| !cancelled.contains(&t.id))
+                .cloned()
+                .collect(),
+            step_outputs: self.step_outputs.clone(),
+            schedule: self.schedule.lock().unwrap().clone(),
+        };
+
+        if let Err(e) = self.store.save(&snapshot) {
+            eprintln!("Failed to persist conversion queue: {}", e);
+        }
+    }
+
+    /// Re-evaluates the schedule window, suspending running tasks when it
+    /// closes and resuming the ones it suspended when it opens. Returns
+    /// whether the window changed.
+    fn refresh_window(&mut self) -> bool {
+        let schedule = self.schedule.lock().unwrap().clone();
+        let open = window_is_open(&schedule, local_minutes_of_day());
+        if open == self.window_open {
+            return false;
+        }
+        self.window_open = open;
+
+        let tasks = self.active_tasks.lock().unwrap();
+        let mut paused = self.paused_tasks.lock().unwrap();
+        let mut held = self.schedule_held.lock().unwrap();
+        if open {
+            let queue_paused = self.queue_paused.load(Ordering::SeqCst);
+            release_held_tasks(&tasks, &mut paused, &mut held, queue_paused);
+        } else {
+            hold_tasks(&tasks, &mut paused, &mut held);
+        }
+        true
+    }
+
+    fn emit_schedule(&self) {
+        let schedule = self.schedule.lock().unwrap().clone();
+        let cancelled = self.cancelled_tasks.lock().unwrap().clone();
+        let waiting = self
+            .queue
+            .iter()
+            .filter(|t| !cancelled.contains(&t.id))
+            .count();
+        let reason = (!self.window_open).then(|| {
+            format!(
+                "Waiting for the processing window {}–{}",
+                schedule.start, schedule.end
+            )
+        });
+        let payload = SchedulePayload {
+            enabled: schedule.enabled,
+            open: self.window_open,
+            next_change_minutes: minutes_until_window_change(&schedule, local_minutes_of_day()),
+            start: schedule.start,
+            end: schedule.end,
+            waiting,
+            held: self.schedule_held.lock().unwrap().len(),
+            reason,
+        };
+        let _ = self.app.emit("conversion-schedule", payload);
+    }
+
+    fn cost_in_use(&self) -> TaskCost {
+        self.running_tasks
+            .values()
+            .fold(TaskCost::default(), |total, running| {
+                add_cost(total, running.cost)
+            })
+    }
+
+    async fn process_queue(&mut self) {
+        if self.queue_paused.load(Ordering::SeqCst) || !self.window_open {
+            return;
+        }
+        let limit = self.max_concurrency.load(Ordering::SeqCst).max(1);
+        let scheduler = self.scheduler.lock().unwrap().clone();
+
+        while let Some(next) = self.queue.front() {
+            let cost = task_cost(&next.config);
+            let admitted = self.cancelled_tasks.lock().unwrap().contains(&next.id)
+                || match scheduler.mode {
+                    SchedulerMode::Fixed => self.running_tasks.len() < limit,
+                    // Only the head is considered, so a cheap task never
+                    // overtakes an expensive one waiting for room.
+                    SchedulerMode::Adaptive => fits_budget(
+                        &scheduler,
+                        self.cost_in_use(),
+                        cost,
+                        self.running_tasks.len(),
+                    ),
+                };
+            if !admitted {
+                break;
+            }
+
+            if let Some(task) = self.queue.pop_front() {
+                self.queued_ids.remove(&task.id);
+                let is_cancelled = {
+                    let mut cancelled = self.cancelled_tasks.lock().unwrap();
+                    cancelled.remove(&task.id)
+                };
+                if is_cancelled {
+                    continue;
+                }
+
+                let Some((output_path, additional_paths)) = self.claim_output_path(&task) else {
+                    continue;
+                };
+
+                self.running_tasks.insert(
+                    task.id.clone(),
+                    RunningTask {
+                        task: task.clone(),
+                        output_path: output_path.clone(),
+                        additional_paths: additional_paths.iter().flatten().cloned().collect(),
+                        started_at: unix_millis(),
+                        progress: 0.0,
+                        cost,
+                    },
+                );
+
+                let (stop_tx, stop_rx) = watch::channel(false);
+                self.stop_signals
+                    .lock()
+                    .unwrap()
+                    .insert(task.id.clone(), stop_tx);
+                self.active_tasks
+                    .lock()
+                    .unwrap()
+                    .insert(task.id.clone(), Vec::new());
+                let processes = TaskProcesses::new(
+                    &task.id,
+                    &self.active_tasks,
+                    &self.cancelled_tasks,
+                    &self.paused_tasks,
+                )
+                .with_priority(task.config.process_priority);
+
+                if let Some(job) = &task.job {
+                    self.emit_pipeline(&job.job_id, &task.id, TaskStatus::Running);
+                }
+
+                let app_clone = self.app.clone();
+                let tx_worker = self.tx.clone();
+                let task_clone = task.clone();
+
+                tauri::async_runtime::spawn(async move {
+                    if let Err(e) = run_ffmpeg_worker(
+                        app_clone,
+                        tx_worker.clone(),
+                        task_clone.clone(),
+                        output_path,
+                        additional_paths,
+                        stop_rx,
+                        processes,
+                    )
+                    .await
+                    {
+                        let _ = tx_worker
+                            .send(ManagerMessage::TaskError(task_clone.id, e))
+                            .await;
+                    } else {
+                        let _ = tx_worker
+                            .send(ManagerMessage::TaskCompleted(task_clone.id))
+                            .await;
+                    }
+                });
+            } else {
+                break;
+            }
+        }
+    }
+}
+
+/// The `get_queue_state` entry for a task that has not started; callers fill in
+/// the timing, progress and position fields that apply to its status.
+pub(crate) fn task_state(task: &ConversionTask, status: TaskStatus) -> TaskState {
+    TaskState {
+        id: task.id.clone(),
+        file_path: task.file_path.clone(),
+        output_name: task.output_name.clone(),
+        job_id: task.job.as_ref().map(|job| job.job_id.clone()),
+        status,
+        priority: task.priority,
+        position: None,
+        pid: None,
+        started_at: None,
+        finished_at: None,
+        progress: 0.0,
+        error: None,
+    }
+}
+
+/// Resolves the additional outputs of `task` next to its claimed main output,
+/// reserving each path taken so concurrent tasks and sibling renditions never
+/// write the same file. Pairs the requested path with the claimed one, or
+/// `None` when the rendition is skipped.
+pub(crate) fn claim_additional_outputs(
+    task: &ConversionTask,
+    output_path: &str,
+    reserved: &mut HashSet<String>,
+    exists: impl Fn(&str) -> bool,
+) -> Result<Vec<(String, Option<String>)>, ConversionError> {
+    reserved.insert(output_path.to_string());
+    let mut claimed = Vec::with_capacity(task.config.additional_outputs.len());
+    for spec in &task.config.additional_outputs {
+        let variant = output_variant_config(&task.config, spec);
+        let path = additional_output_path(output_source(task), output_path, spec, &variant);
+        let resolved = resolve_output_collision(&path, task.collision_policy, reserved, &exists)?;
+        if let Some(resolved) = &resolved {
+            reserved.insert(resolved.clone());
+        }
+        claimed.push((path, resolved));
+    }
+    Ok(claimed)
+}
+
+/// Index ahead of the first queued task with a lower priority, so equal
+/// priorities keep their arrival order. Manual moves can still put a task
+/// anywhere afterwards.
+pub(crate) fn priority_insert_index(queue: &VecDeque<ConversionTask>, priority: i32) -> usize {
+    queue
+        .iter()
+        .position(|queued| queued.priority < priority)
+        .unwrap_or(queue.len())
+}
+
+/// Delay before the next attempt of a task that has now failed `attempts`
+/// times, or `None` once the policy gives up or the error is not retryable.
+pub(crate) fn retry_delay_ms(policy: &RetryPolicy, attempts: u32, kind: &str) -> Option<u64> {
+    if attempts >= policy.max_attempts || !policy.retryable_kinds.iter().any(|k| k == kind) {
+        return None;
+    }
+    let factor = policy
+        .backoff_multiplier
+        .max(1.0)
+        .powi(attempts.saturating_sub(1) as i32);
+    Some((policy.backoff_ms as f64 * factor).min(MAX_RETRY_BACKOFF_MS as f64) as u64)
+}
+
+pub(crate) fn validate_scheduler_config(
+    scheduler: &SchedulerConfig,
+) -> Result<(), ConversionError> {
+    let valid = |budget: f64| budget.is_finite() && budget > 0.0;
+    if !valid(scheduler.cpu_budget) || !valid(scheduler.gpu_budget) {
+        return Err(ConversionError::InvalidInput(
+            "Scheduler budgets must be positive".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+pub(crate) fn validate_queue_schedule(schedule: &QueueSchedule) -> Result<(), ConversionError> {
+    for value in [&schedule.start, &schedule.end] {
+        if parse_clock(value).is_none() {
+            return Err(ConversionError::InvalidInput(format!(
+                "Invalid schedule time '{}', expected HH:MM",
+                value
+            )));
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn validate_retry_policy(policy: &RetryPolicy) -> Result<(), ConversionError> {
+    if policy.max_attempts == 0 {
+        return Err(ConversionError::InvalidInput(
+            "Max attempts must be at least 1".to_string(),
+        ));
+    }
+    if !policy.backoff_multiplier.is_finite() || policy.backoff_multiplier < 1.0 {
+        return Err(ConversionError::InvalidInput(
+            "Backoff multiplier must be at least 1".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Suspends the active tasks the user has not paused when the window closes.
+/// Failures are ignored; a task that keeps running just finishes early.
+pub(crate) fn hold_tasks(
+    active: &HashMap<String, Vec<u32>>,
+    paused: &mut HashSet<String>,
+    held: &mut HashSet<String>,
+) {
+    for (id, pids) in active {
+        // Tasks the user paused stay theirs to resume.
+        if paused.contains(id) {
+            continue;
+        }
+        for &pid in pids {
+            let _ = suspend_process(pid);
+        }
+        paused.insert(id.clone());
+        held.insert(id.clone());
+    }
+}
+
+/// Resumes the tasks the schedule suspended when the window opens. While
+/// `pause_all` is in effect they are handed over to it instead, staying
+/// suspended until `resume_all`.
+pub(crate) fn release_held_tasks(
+    active: &HashMap<String, Vec<u32>>,
+    paused: &mut HashSet<String>,
+    held: &mut HashSet<String>,
+    queue_paused: bool,
+) {
+    if queue_paused {
+        held.clear();
+        return;
+    }
+    for id in held.drain() {
+        for &pid in active.get(&id).into_iter().flatten() {
+            let _ = resume_process(pid);
+        }
+        paused.remove(&id);
+    }
+}
+
+/// Suspends every process of every active task and marks the tasks paused.
+/// A process that cannot be signalled does not stop the others; the last
+/// error is returned.
+pub(crate) fn suspend_tasks(
+    active: &HashMap<String, Vec<u32>>,
+    paused: &mut HashSet<String>,
+) -> Result<(), ConversionError> {
+    let mut result = Ok(());
+    for (id, pids) in active {
+        for &pid in pids {
+            if let Err(e) = suspend_process(pid) {
+                result = Err(e);
+            }
+        }
+        paused.insert(id.clone());
+    }
+    result
+}
+
+/// Resumes the active tasks in `paused` and clears their mark; running tasks
+/// and tasks in `held` are left alone.
+pub(crate) fn resume_paused_tasks(
+    active: &HashMap<String, Vec<u32>>,
+    paused: &mut HashSet<String>,
+    held: &HashSet<String>,
+) -> Result<(), ConversionError> {
+    let mut result = Ok(());
+    for (id, pids) in active {
+        if held.contains(id) || !paused.remove(id) {
+            continue;
+        }
+        for &pid in pids {
+            if let Err(e) = resume_process(pid) {
+                result = Err(e);
+            }
+        }
+    }
+    result
+}
+
+fn suspend_process(pid: u32) -> Result<(), ConversionError> {
+    if pid == 0 {
+        return Ok(());
+    }
+
+    #[cfg(unix)]
+    unsafe {
+        if libc::kill(pid as libc::pid_t, libc::SIGSTOP) != 0 {
+            return Err(ConversionError::Shell("Failed to send SIGSTOP".to_string()));
+        }
+    }
+
+    #[cfg(windows)]
+    unsafe {
+        windows_suspend_resume(pid, true)?;
+    }
+
+    Ok(())
+}
+
+fn resume_process(pid: u32) -> Result<(), ConversionError> {
+    if pid == 0 {
+        return Ok(());
+    }
+
+    #[cfg(unix)]
+    unsafe {
+        if libc::kill(pid as libc::pid_t, libc::SIGCONT) != 0 {
+            return Err(ConversionError::Shell("Failed to send SIGCONT".to_string()));
+        }
+    }
+
+    #[cfg(windows)]
+    unsafe {
+        windows_suspend_resume(pid, false)?;
+    }
+
+    Ok(())
+}
+
+/// Renices a sidecar (Unix) or sets its priority class (Windows). `Normal`
+/// keeps the priority inherited from the app; raising it above normal can
+/// fail without elevated rights.
+pub(crate) fn set_process_priority(
+    pid: u32,
+    priority: ProcessPriority,
+) -> Result<(), ConversionError> {
+    if pid == 0 || priority == ProcessPriority::Normal {
+        return Ok(());
+    }
+
+    #[cfg(unix)]
+    unsafe {
+        let niceness = match priority {
+            ProcessPriority::Low => 10,
+            ProcessPriority::Normal => 0,
+            ProcessPriority::High => -5,
+        };
+        if libc::setpriority(libc::PRIO_PROCESS, pid as libc::id_t, niceness) != 0 {
+            return Err(ConversionError::Shell(format!(
+                "Failed to set niceness {}: {}",
+                niceness,
+                std::io::Error::last_os_error()
+            )));
+        }
+    }
+
+    #[cfg(windows)]
+    unsafe {
+        let class = match priority {
+            ProcessPriority::Low => BELOW_NORMAL_PRIORITY_CLASS,
+            ProcessPriority::Normal => NORMAL_PRIORITY_CLASS,
+            ProcessPriority::High => ABOVE_NORMAL_PRIORITY_CLASS,
+        };
+        let process_handle = OpenProcess(PROCESS_SET_INFORMATION, false, pid)
+            .map_err(|e| ConversionError::Shell(format!("Failed to open process: {}", e)))?;
+        let result = SetPriorityClass(process_handle, class);
+        let _ = CloseHandle(process_handle);
+        result
+            .map_err(|e| ConversionError::Shell(format!("Failed to set priority class: {}", e)))?;
+    }
+
+    Ok(())
+}
+
+fn unix_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
 #[cfg(windows)]
 unsafe fn windows_suspend_resume(pid: u32, suspend: bool) -> Result<(), ConversionError> {
     let process_handle = OpenProcess(PROCESS_SUSPEND_RESUME, false, pid)
@@ -98,6 +98,26 @@ pub fn container_supports_subtitles(container: &str) -> bool {
     !is_audio_only_container(container) && !is_video_only_container(container)
 }
 
+/// ffprobe reports bitstream codec names while the rules list encoder names;
+/// map the former onto an encoder that produces the same bitstream.
+fn encoder_for_source_codec(codec: &str) -> &str {
+    match codec {
+        "h264" => "libx264",
+        "hevc" => "libx265",
+        "av1" => "libsvtav1",
+        "opus" => "libopus",
+        other => other,
+    }
+}
+
+pub fn is_video_stream_copy_allowed(container: &str, source_codec: &str) -> bool {
+    is_video_codec_allowed(container, encoder_for_source_codec(source_codec))
+}
+
+pub fn is_audio_stream_copy_allowed(container: &str, source_codec: &str) -> bool {
+    is_audio_codec_allowed(container, encoder_for_source_codec(source_codec))
+}
+
 pub fn is_audio_codec_allowed(container: &str, codec: &str) -> bool {
     let container = container.to_ascii_lowercase();
     match MEDIA_RULES
@@ -1,14 +1,21 @@
 pub(crate) mod args;
+pub mod cli;
 pub(crate) mod codec;
 pub mod commands;
+pub(crate) mod diagnostics;
 pub mod error;
 pub(crate) mod filters;
 pub(crate) mod manager;
 pub(crate) mod media_rules;
+pub(crate) mod pipeline;
 mod probe;
+pub(crate) mod progress;
+pub(crate) mod scheduler;
+pub(crate) mod store;
 pub(crate) mod types;
 pub(crate) mod upscale;
 pub(crate) mod utils;
+pub(crate) mod verify;
 pub(crate) mod worker;
 
 #[cfg(test)]
@@ -0,0 +1,159 @@
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use crate::conversion::error::ConversionError;
+use crate::conversion::types::{ConversionTask, PipelineStep, StepOutput};
+
+/// Checks a job before any of it is queued. Steps may only depend on steps
+/// listed before them, which keeps the graph acyclic, and a step's input must
+/// come from one of its dependencies so it never starts before that file exists.
+pub fn validate_pipeline(steps: &[PipelineStep]) -> Result<(), ConversionError> {
+    if steps.is_empty() {
+        return Err(ConversionError::InvalidInput(
+            "A pipeline needs at least one step".to_string(),
+        ));
+    }
+
+    let mut earlier: HashSet<&str> = HashSet::new();
+    for step in steps {
+        if step.id.trim().is_empty() {
+            return Err(ConversionError::InvalidInput(
+                "Pipeline step ids cannot be empty".to_string(),
+            ));
+        }
+        if let Some(dependency) = step
+            .depends_on
+            .iter()
+            .find(|dependency| !earlier.contains(dependency.as_str()))
+        {
+            return Err(ConversionError::InvalidInput(format!(
+                "Step {} depends on {}, which is not an earlier step",
+                step.id, dependency
+            )));
+        }
+        if let Some(input) = step
+            .input_from
+            .as_ref()
+            .filter(|input| !step.depends_on.contains(input))
+        {
+            return Err(ConversionError::InvalidInput(format!(
+                "Step {} reads the output of {} without depending on it",
+                step.id, input
+            )));
+        }
+        if !earlier.insert(step.id.as_str()) {
+            return Err(ConversionError::InvalidInput(format!(
+                "Duplicate pipeline step id: {}",
+                step.id
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether every step `task` depends on has produced its output.
+pub fn is_ready(task: &ConversionTask, outputs: &HashMap<String, StepOutput>) -> bool {
+    task.job
+        .as_ref()
+        .is_none_or(|job| job.depends_on.iter().all(|id| outputs.contains_key(id)))
+}
+
+/// Points a ready step at the output it reads, if it reads one.
+pub fn resolve_step_input(task: &mut ConversionTask, outputs: &HashMap<String, StepOutput>) {
+    if let Some(output) = task
+        .job
+        .as_ref()
+        .and_then(|job| job.input_from.as_ref())
+        .and_then(|id| outputs.get(id))
+    {
+        task.file_path = output.path.clone();
+    }
+}
+
+/// The output a pipeline step leaves at `output_path`. Only a file the job
+/// `created` can be an intermediate; one that already existed is never deleted.
+pub fn step_output(task: &ConversionTask, output_path: &str, created: bool) -> Option<StepOutput> {
+    task.job.as_ref().map(|job| StepOutput {
+        job_id: job.job_id.clone(),
+        path: output_path.to_string(),
+        intermediate: job.intermediate && created,
+    })
+}
+
+/// Takes the steps in `blocked` that wait on `step_id`, directly or through
+/// another step taken with them.
+pub fn take_dependents(blocked: &mut Vec<ConversionTask>, step_id: &str) -> Vec<ConversionTask> {
+    let mut taken = Vec::new();
+    let mut upstream = vec![step_id.to_string()];
+    while let Some(id) = upstream.pop() {
+        let (dependents, rest): (Vec<_>, Vec<_>) =
+            std::mem::take(blocked).into_iter().partition(|task| {
+                task.job
+                    .as_ref()
+                    .is_some_and(|job| job.depends_on.contains(&id))
+            });
+        *blocked = rest;
+        upstream.extend(dependents.iter().map(|task| task.id.clone()));
+        taken.extend(dependents);
+    }
+    taken
+}
+
+/// The path a task's output is named and placed after: the job's source for
+/// pipeline steps, since `file_path` may point at an intermediate by then.
+pub fn output_source(task: &ConversionTask) -> &str {
+    task.job
+        .as_ref()
+        .map(|job| job.source_path.as_str())
+        .filter(|path| !path.is_empty())
+        .unwrap_or(&task.file_path)
+}
+
+/// Intermediate outputs of `job_id` that none of the still pending tasks depend on.
+pub fn unused_intermediates<'a>(
+    job_id: &str,
+    outputs: &HashMap<String, StepOutput>,
+    pending: impl Iterator<Item = &'a ConversionTask>,
+) -> Vec<String> {
+    let needed: HashSet<&str> = pending
+        .filter_map(|task| task.job.as_ref())
+        .filter(|job| job.job_id == job_id)
+        .flat_map(|job| job.depends_on.iter().map(String::as_str))
+        .collect();
+
+    let mut unused: Vec<String> = outputs
+        .iter()
+        .filter(|(id, output)| {
+            output.job_id == job_id && output.intermediate && !needed.contains(id.as_str())
+        })
+        .map(|(id, _)| id.clone())
+        .collect();
+    unused.sort();
+    unused
+}
+
+/// Deletes the intermediate files of `job_id` that no pending task reads any
+/// more. Once nothing of the job is pending its outputs are forgotten and
+/// `temp_dir` is removed if empty; files not recorded as intermediate are
+/// never touched.
+pub fn collect_job_outputs(
+    job_id: &str,
+    outputs: &mut HashMap<String, StepOutput>,
+    pending: &[ConversionTask],
+    temp_dir: &Path,
+) {
+    for id in unused_intermediates(job_id, outputs, pending.iter()) {
+        if let Some(output) = outputs.remove(&id) {
+            let _ = std::fs::remove_file(&output.path);
+        }
+    }
+
+    let job_pending = pending
+        .iter()
+        .any(|task| task.job.as_ref().is_some_and(|job| job.job_id == job_id));
+    if !job_pending {
+        outputs.retain(|_, output| output.job_id != job_id);
+        let _ = std::fs::remove_dir(temp_dir);
+    }
+}
@@ -9,21 +9,11 @@ pub async fn probe_media_file(
     app: &AppHandle,
     file_path: &str,
 ) -> Result<ProbeMetadata, ConversionError> {
-    let args = vec![
-        "-v".to_string(),
-        "quiet".to_string(),
-        "-print_format".to_string(),
-        "json".to_string(),
-        "-show_format".to_string(),
-        "-show_streams".to_string(),
-        file_path.to_string(),
-    ];
-
     let output = app
         .shell()
         .sidecar("ffprobe")
         .map_err(|e| ConversionError::Shell(e.to_string()))?
-        .args(args)
+        .args(probe_args(file_path))
         .output()
         .await
         .map_err(|e| ConversionError::Shell(e.to_string()))?;
@@ -33,8 +23,25 @@ pub async fn probe_media_file(
         return Err(ConversionError::Probe(stderr));
     }
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let probe_data: FfprobeOutput = serde_json::from_str(&stdout)?;
+    parse_probe_output(&String::from_utf8_lossy(&output.stdout))
+}
+
+pub fn probe_args(file_path: &str) -> Vec<String> {
+    vec![
+        "-v".to_string(),
+        "quiet".to_string(),
+        "-print_format".to_string(),
+        "json".to_string(),
+        "-show_format".to_string(),
+        "-show_streams".to_string(),
+        file_path.to_string(),
+    ]
+}
+
+/// Turns `ffprobe -print_format json -show_format -show_streams` output into
+/// the metadata the UI and the argument builders work with.
+pub fn parse_probe_output(stdout: &str) -> Result<ProbeMetadata, ConversionError> {
+    let probe_data: FfprobeOutput = serde_json::from_str(stdout)?;
 
     let mut metadata = ProbeMetadata::default();
 
@@ -129,3 +136,65 @@ pub async fn probe_media_file(
 
     Ok(metadata)
 }
+
+const KEYFRAME_SEARCH_WINDOW: f64 = 30.0;
+
+/// Keyframe timestamps of the first video stream in a window ending at `until`.
+/// ffprobe seeks to the keyframe at or before the window start, so the result
+/// always contains the keyframe that precedes `until` when one exists.
+pub async fn probe_keyframes(
+    app: &AppHandle,
+    file_path: &str,
+    until: f64,
+) -> Result<Vec<f64>, ConversionError> {
+    let output = app
+        .shell()
+        .sidecar("ffprobe")
+        .map_err(|e| ConversionError::Shell(e.to_string()))?
+        .args(keyframe_probe_args(file_path, until))
+        .output()
+        .await
+        .map_err(|e| ConversionError::Shell(e.to_string()))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+        return Err(ConversionError::Probe(stderr));
+    }
+
+    Ok(parse_keyframe_packets(&String::from_utf8_lossy(
+        &output.stdout,
+    )))
+}
+
+pub fn keyframe_probe_args(file_path: &str, until: f64) -> Vec<String> {
+    let window_start = (until - KEYFRAME_SEARCH_WINDOW).max(0.0);
+    vec![
+        "-v".to_string(),
+        "error".to_string(),
+        "-select_streams".to_string(),
+        "v:0".to_string(),
+        "-read_intervals".to_string(),
+        format!("{:.3}%{:.3}", window_start, until + 0.001),
+        "-show_entries".to_string(),
+        "packet=pts_time,flags".to_string(),
+        "-of".to_string(),
+        "csv=p=0".to_string(),
+        file_path.to_string(),
+    ]
+}
+
+/// Parses `pts_time,flags` CSV rows and keeps the packets flagged as keyframes (`K`).
+pub fn parse_keyframe_packets(output: &str) -> Vec<f64> {
+    let mut keyframes: Vec<f64> = output
+        .lines()
+        .filter_map(|line| {
+            let (pts, flags) = line.trim().split_once(',')?;
+            if !flags.starts_with('K') {
+                return None;
+            }
+            pts.parse::<f64>().ok()
+        })
+        .collect();
+    keyframes.sort_by(|a, b| a.total_cmp(b));
+    keyframes
+}
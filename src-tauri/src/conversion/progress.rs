@@ -0,0 +1,84 @@
+use crate::conversion::utils::parse_time;
+
+/// One `-progress` block as reported by ffmpeg. Fields that ffmpeg reports
+/// as `N/A` (common during the first block and on some muxers) stay `None`.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FfmpegProgress {
+    pub frame: Option<u64>,
+    pub fps: Option<f64>,
+    pub bitrate_kbps: Option<f64>,
+    pub total_size: Option<u64>,
+    pub out_time: Option<f64>,
+    pub speed: Option<f64>,
+    pub finished: bool,
+}
+
+/// Accumulates the `key=value` lines written by `ffmpeg -progress pipe:1`
+/// and yields a complete snapshot each time a `progress=` line closes a block.
+#[derive(Debug, Default)]
+pub struct ProgressParser {
+    current: FfmpegProgress,
+}
+
+impl ProgressParser {
+    pub fn push_line(&mut self, line: &str) -> Option<FfmpegProgress> {
+        let (key, value) = line.trim().split_once('=')?;
+        let value = value.trim();
+
+        match key.trim() {
+            "frame" => self.current.frame = value.parse().ok(),
+            "fps" => self.current.fps = parse_progress_number(value),
+            "bitrate" => {
+                self.current.bitrate_kbps = parse_progress_number(value.trim_end_matches("kbits/s"))
+            }
+            "total_size" => self.current.total_size = value.parse().ok(),
+            // `out_time_ms` is also in microseconds; both are kept by ffmpeg for compatibility.
+            "out_time_us" | "out_time_ms" => {
+                if let Some(us) = value.parse::<i64>().ok().filter(|us| *us >= 0) {
+                    self.current.out_time = Some(us as f64 / 1_000_000.0);
+                }
+            }
+            "out_time" if self.current.out_time.is_none() && !value.starts_with('-') => {
+                self.current.out_time = parse_time(value);
+            }
+            "speed" => self.current.speed = parse_progress_number(value.trim_end_matches('x')),
+            "progress" => {
+                self.current.finished = value == "end";
+                return Some(std::mem::take(&mut self.current));
+            }
+            _ => {}
+        }
+
+        None
+    }
+}
+
+fn parse_progress_number(value: &str) -> Option<f64> {
+    let value = value.trim();
+    if value.is_empty() || value.eq_ignore_ascii_case("n/a") {
+        return None;
+    }
+    value
+        .parse::<f64>()
+        .ok()
+        .filter(|v| v.is_finite() && *v >= 0.0)
+}
+
+/// Remaining wall-clock seconds for an encode. Prefers ffmpeg's reported
+/// speed and falls back to the average rate observed since the task started.
+pub fn estimate_eta(duration: f64, out_time: f64, speed: Option<f64>, elapsed: f64) -> Option<f64> {
+    if duration <= 0.0 {
+        return None;
+    }
+
+    let remaining = (duration - out_time).max(0.0);
+    if let Some(speed) = speed.filter(|s| *s > 0.0) {
+        return Some(remaining / speed);
+    }
+
+    if out_time > 0.0 && elapsed > 0.0 {
+        return Some(remaining * elapsed / out_time);
+    }
+
+    None
+}
@@ -0,0 +1,135 @@
+use crate::conversion::args::{output_height, output_variant_config};
+use crate::conversion::types::{ConversionConfig, QueueSchedule, SchedulerConfig, TaskCost};
+use crate::conversion::utils::{
+    is_audio_only_container, is_nvenc_codec, is_stream_copy, is_videotoolbox_codec,
+};
+
+/// Output height assumed when the task keeps the source resolution, which is
+/// not known until the worker probes the input.
+const REFERENCE_HEIGHT: f64 = 1080.0;
+
+/// Slack for summing fractional costs, so tasks that exactly fill a budget fit.
+const BUDGET_EPSILON: f64 = 1e-9;
+
+/// Estimates what a task will use from its config alone. Software encodes
+/// scale with the output pixel count and the encoder's complexity; hardware
+/// encodes take one encoder session and little CPU; ML upscaling occupies the
+/// GPU on top of its decode and encode stages.
+pub fn task_cost(config: &ConversionConfig) -> TaskCost {
+    let upscaling = config
+        .ml_upscale
+        .as_deref()
+        .is_some_and(|mode| !mode.is_empty() && mode != "none");
+
+    if is_audio_only_container(&config.container) {
+        return TaskCost {
+            cpu: 0.25,
+            gpu: 0.0,
+        };
+    }
+    if is_stream_copy(&config.video_codec) {
+        return TaskCost { cpu: 0.1, gpu: 0.0 };
+    }
+
+    let height = output_height(config, None).map_or(REFERENCE_HEIGHT, f64::from);
+    let pixels = (height / REFERENCE_HEIGHT).powi(2).clamp(0.25, 4.0);
+
+    let mut cost =
+        if is_nvenc_codec(&config.video_codec) || is_videotoolbox_codec(&config.video_codec) {
+            TaskCost {
+                cpu: 0.25,
+                gpu: 1.0,
+            }
+        } else {
+            TaskCost {
+                cpu: software_encoder_weight(&config.video_codec) * pixels,
+                gpu: 0.0,
+            }
+        };
+
+    if upscaling {
+        cost.cpu += 0.5;
+        cost.gpu += 2.0;
+    }
+
+    // Renditions share the decode but each one runs its own encoder.
+    config
+        .additional_outputs
+        .iter()
+        .map(|spec| task_cost(&output_variant_config(config, spec)))
+        .fold(cost, add_cost)
+}
+
+/// CPU cost of a software encoder relative to libx264 at the same resolution.
+fn software_encoder_weight(codec: &str) -> f64 {
+    match codec {
+        "libx265" | "libsvtav1" | "vp9" | "libvpx-vp9" => 2.0,
+        "gif" => 0.5,
+        _ => 1.0,
+    }
+}
+
+pub fn add_cost(a: TaskCost, b: TaskCost) -> TaskCost {
+    TaskCost {
+        cpu: a.cpu + b.cpu,
+        gpu: a.gpu + b.gpu,
+    }
+}
+
+/// Whether a task costing `next` may start beside running tasks that use
+/// `in_use`. With nothing running any task starts, so one that exceeds a
+/// budget on its own still runs, alone.
+pub fn fits_budget(
+    scheduler: &SchedulerConfig,
+    in_use: TaskCost,
+    next: TaskCost,
+    running: usize,
+) -> bool {
+    let total = add_cost(in_use, next);
+    running == 0
+        || (total.cpu <= scheduler.cpu_budget + BUDGET_EPSILON
+            && total.gpu <= scheduler.gpu_budget + BUDGET_EPSILON)
+}
+
+const MINUTES_PER_DAY: u32 = 24 * 60;
+
+/// Parses `HH:MM` into minutes since midnight.
+pub fn parse_clock(value: &str) -> Option<u32> {
+    let (hours, minutes) = value.trim().split_once(':')?;
+    let hours: u32 = hours.parse().ok()?;
+    let minutes: u32 = minutes.parse().ok()?;
+    (hours < 24 && minutes < 60).then_some(hours * 60 + minutes)
+}
+
+/// Whether tasks may run at `now` (minutes since local midnight). A disabled
+/// or unparsable schedule never holds the queue; equal start and end leave
+/// the window open all day.
+pub fn window_is_open(schedule: &QueueSchedule, now: u32) -> bool {
+    let (Some(start), Some(end)) = (parse_clock(&schedule.start), parse_clock(&schedule.end))
+    else {
+        return true;
+    };
+    if !schedule.enabled || start == end {
+        return true;
+    }
+    if start < end {
+        (start..end).contains(&now)
+    } else {
+        now >= start || now < end
+    }
+}
+
+/// Minutes from `now` until the window next opens or closes.
+pub fn minutes_until_window_change(schedule: &QueueSchedule, now: u32) -> Option<u32> {
+    let start = parse_clock(&schedule.start)?;
+    let end = parse_clock(&schedule.end)?;
+    if !schedule.enabled || start == end {
+        return None;
+    }
+    let target = if window_is_open(schedule, now) {
+        end
+    } else {
+        start
+    };
+    Some((target + MINUTES_PER_DAY - now % MINUTES_PER_DAY) % MINUTES_PER_DAY)
+}
@@ -0,0 +1,87 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::conversion::error::ConversionError;
+use crate::conversion::types::{ConversionTask, QueueSchedule, StepOutput};
+
+const JOB_STORE_FILE: &str = "conversion-queue.json";
+
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PersistedQueue {
+    #[serde(default)]
+    pub queued: Vec<ConversionTask>,
+    #[serde(default)]
+    pub running: Vec<ConversionTask>,
+    #[serde(default)]
+    pub interrupted: Vec<ConversionTask>,
+    /// Kept with the queue so restored tasks wait for the window from launch.
+    #[serde(default)]
+    pub schedule: QueueSchedule,
+    /// Pipeline steps waiting for earlier steps.
+    #[serde(default)]
+    pub blocked: Vec<ConversionTask>,
+    /// Outputs of finished pipeline steps that later steps still read.
+    #[serde(default)]
+    pub step_outputs: HashMap<String, StepOutput>,
+}
+
+impl PersistedQueue {
+    /// Tasks that were running when the store was last written did not finish,
+    /// so on restore they are reported as interrupted instead of being restarted.
+    pub fn into_restored(self) -> (Vec<ConversionTask>, Vec<ConversionTask>) {
+        let mut interrupted = self.interrupted;
+        for task in self.running {
+            if !interrupted.iter().any(|existing| existing.id == task.id) {
+                interrupted.push(task);
+            }
+        }
+        (self.queued, interrupted)
+    }
+}
+
+pub struct JobStore {
+    path: Option<PathBuf>,
+}
+
+impl JobStore {
+    pub fn new(dir: Option<PathBuf>) -> Self {
+        Self {
+            path: dir.map(|dir| dir.join(JOB_STORE_FILE)),
+        }
+    }
+
+    pub fn load(&self) -> PersistedQueue {
+        let Some(path) = &self.path else {
+            return PersistedQueue::default();
+        };
+
+        let raw = match std::fs::read_to_string(path) {
+            Ok(raw) => raw,
+            Err(_) => return PersistedQueue::default(),
+        };
+
+        serde_json::from_str(&raw).unwrap_or_else(|e| {
+            eprintln!("Ignoring unreadable job store {}: {}", path.display(), e);
+            PersistedQueue::default()
+        })
+    }
+
+    pub fn save(&self, snapshot: &PersistedQueue) -> Result<(), ConversionError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        // Write to a sibling file first so a crash mid-write never leaves a truncated store.
+        let temp_path = path.with_extension("json.tmp");
+        std::fs::write(&temp_path, serde_json::to_vec_pretty(snapshot)?)?;
+        std::fs::rename(&temp_path, path)?;
+        Ok(())
+    }
+}
@@ -1,33 +1,23 @@
 #[cfg(test)]
-mod tests {
-    use crate::conversion::args::{build_ffmpeg_args, build_output_path, validate_task_input};
-    use crate::conversion::types::{ConversionConfig, MetadataConfig, MetadataMode};
-    use crate::conversion::upscale::build_upscale_encode_args;
-    use crate::conversion::utils::parse_time;
-    use std::fs;
-    use std::path::PathBuf;
-    use std::time::{SystemTime, UNIX_EPOCH};
-
-    fn contains_args(args: &[String], expected: &[&str]) -> bool {
-        expected.iter().all(|e| args.iter().any(|a| a == e))
-    }
-
-    fn contains_arg_pair(args: &[String], first: &str, second: &str) -> bool {
-        args.windows(2)
-            .any(|window| window[0] == first && window[1] == second)
-    }
+pub(crate) mod test_helpers {
+    use crate::conversion::types::{
+        AudioTrack, CollisionPolicy, ConversionConfig, ConversionTask, MetadataConfig,
+        ProbeMetadata, ProcessPriority,
+    };
 
-    fn sample_config(container: &str) -> ConversionConfig {
+    /// An H.264/AAC MP4 encode with every option at its default.
+    pub(crate) fn config() -> ConversionConfig {
         ConversionConfig {
-            container: container.into(),
+            container: "mp4".into(),
             video_codec: "libx264".into(),
             video_bitrate_mode: "crf".into(),
             video_bitrate: "5000".into(),
+            target_size_mb: None,
             audio_codec: "aac".into(),
             audio_bitrate: "128".into(),
             audio_channels: "original".into(),
             audio_volume: 100.0,
-            selected_audio_tracks: vec![1],
+            selected_audio_tracks: vec![],
             selected_subtitle_tracks: vec![],
             subtitle_burn_path: None,
             resolution: "original".into(),
@@ -40,6 +30,7 @@ mod tests {
             preset: "medium".into(),
             start_time: None,
             end_time: None,
+            segments: vec![],
             audio_normalize: false,
             metadata: MetadataConfig::default(),
             rotation: "0".into(),
@@ -54,6 +45,86 @@ mod tests {
             gif_colors: 256,
             gif_dither: "sierra2_4a".into(),
             gif_loop: 0,
+            two_pass: false,
+            hw_fallback: false,
+            output_directory: None,
+            output_template: None,
+            threads: None,
+            process_priority: ProcessPriority::Normal,
+            additional_outputs: vec![],
+        }
+    }
+
+    /// A probe with an H.264 stream when `video` is set and `audio_tracks`
+    /// stereo AAC tracks.
+    pub(crate) fn media(video: bool, audio_tracks: u32, duration: &str) -> ProbeMetadata {
+        ProbeMetadata {
+            duration: Some(duration.into()),
+            video_codec: video.then(|| "h264".to_string()),
+            audio_tracks: (1..=audio_tracks)
+                .map(|index| AudioTrack {
+                    index,
+                    codec: "aac".into(),
+                    channels: "2".into(),
+                    language: None,
+                    label: None,
+                    bitrate_kbps: None,
+                    sample_rate: None,
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn task(id: &str) -> ConversionTask {
+        ConversionTask {
+            id: id.into(),
+            file_path: format!("/tmp/{}.mov", id),
+            output_name: None,
+            config: config(),
+            priority: 0,
+            collision_policy: CollisionPolicy::default(),
+            retry_policy: None,
+            attempts: 0,
+            job: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::conversion::args::{
+        additional_output_path, build_concat_list, build_ffmpeg_args, build_multi_output_args,
+        build_output_path, build_two_pass_args, output_variant_config, render_output_template,
+        resolve_output_collision, resolve_output_path, resolve_target_size, uses_two_pass,
+        validate_task_input,
+    };
+    use crate::conversion::manager::set_process_priority;
+    use crate::conversion::types::{
+        AudioTrack, CollisionPolicy, ConversionConfig, MetadataMode, OutputSpec, ProbeMetadata,
+        ProcessPriority, TimeSegment,
+    };
+    use crate::conversion::upscale::build_upscale_encode_args;
+    use crate::conversion::utils::parse_time;
+    use std::collections::HashSet;
+    use std::fs;
+    use std::path::PathBuf;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn contains_args(args: &[String], expected: &[&str]) -> bool {
+        expected.iter().all(|e| args.iter().any(|a| a == e))
+    }
+
+    fn contains_arg_pair(args: &[String], first: &str, second: &str) -> bool {
+        args.windows(2)
+            .any(|window| window[0] == first && window[1] == second)
+    }
+
+    fn sample_config(container: &str) -> ConversionConfig {
+        ConversionConfig {
+            container: container.into(),
+            selected_audio_tracks: vec![1],
+            ..super::test_helpers::config()
         }
     }
 
@@ -194,6 +265,176 @@ mod tests {
         assert_eq!(default, "/tmp/sample.mov_converted.mp4");
     }
 
+    #[test]
+    fn output_directory_replaces_source_directory() {
+        let mut config = sample_config("mp4");
+        assert_eq!(
+            resolve_output_path("/videos/clip.mov", &config, None),
+            "/videos/clip.mov_converted.mp4"
+        );
+
+        config.output_directory = Some("/exports".into());
+        assert_eq!(
+            resolve_output_path("/videos/clip.mov", &config, None),
+            "/exports/clip.mov_converted.mp4"
+        );
+        assert_eq!(
+            resolve_output_path("/videos/clip.mov", &config, Some("../final".into())),
+            "/exports/final.mp4"
+        );
+    }
+
+    #[test]
+    fn output_template_expands_tokens() {
+        let mut config = sample_config("mkv");
+        config.resolution = "720p".into();
+        let probe = ProbeMetadata {
+            width: Some(1920),
+            height: Some(1080),
+            ..probe_with_codecs("h264", &["aac"])
+        };
+
+        let name = render_output_template(
+            "{name}.{ext} {codec} {height}p {container} {date} {preset}",
+            "/videos/my.clip.mov",
+            &config,
+            Some(&probe),
+            Some("Web Upload"),
+            "2026-01-31",
+        )
+        .unwrap();
+        assert_eq!(
+            name,
+            format!(
+                "my.clip.mov {} 720p mkv 2026-01-31 Web Upload.mkv",
+                config.video_codec
+            )
+        );
+
+        config.resolution = "original".into();
+        config.video_codec = "copy".into();
+        config.rotation = "90".into();
+        let name = render_output_template(
+            "{name}_{codec}_{height}p",
+            "/videos/clip.mov",
+            &config,
+            Some(&probe),
+            None,
+            "2026-01-31",
+        )
+        .unwrap();
+        assert_eq!(name, "clip_h264_1920p.mkv");
+    }
+
+    #[test]
+    fn output_template_keeps_output_in_one_directory() {
+        let config = sample_config("mp4");
+        let name = render_output_template(
+            "../{preset}/{name}",
+            "/videos/clip.mov",
+            &config,
+            None,
+            Some("a/b:c"),
+            "2026-01-31",
+        )
+        .unwrap();
+
+        assert_eq!(
+            build_output_path("/videos/clip.mov", "mp4", Some(name)),
+            "/videos/clip.mp4"
+        );
+        let name = render_output_template(
+            "{preset}",
+            "/videos/clip.mov",
+            &config,
+            None,
+            Some("a/b:c"),
+            "2026-01-31",
+        )
+        .unwrap();
+        assert_eq!(name, "a_b_c.mp4");
+    }
+
+    #[test]
+    fn output_template_rejects_unknown_or_unresolvable_tokens() {
+        let mut config = sample_config("mp4");
+        let render = |template: &str, config: &ConversionConfig| {
+            render_output_template(
+                template,
+                "/videos/clip.mov",
+                config,
+                None,
+                None,
+                "2026-01-31",
+            )
+        };
+
+        assert!(render("{name}_{bitrate}", &config).is_err());
+        assert!(render("{height}p", &config).is_err());
+        assert!(render("   ", &config).is_err());
+
+        config.output_template = Some("{title}".into());
+        let path = create_temp_input_file();
+        let result = validate_task_input(path.to_str().unwrap(), &config, None);
+        config.output_template = None;
+        config.output_directory = Some("/nonexistent/frame-exports".into());
+        let missing_dir = validate_task_input(path.to_str().unwrap(), &config, None);
+        let _ = fs::remove_file(&path);
+
+        assert!(result.is_err());
+        assert!(missing_dir.is_err());
+    }
+
+    #[test]
+    fn collision_policy_resolution() {
+        let on_disk = ["/out/clip.mp4", "/out/clip (1).mp4"];
+        let exists = |path: &str| on_disk.contains(&path);
+        let reserved: HashSet<String> = ["/out/clip (2).mp4".to_string()].into();
+        let resolve =
+            |path: &str, policy| resolve_output_collision(path, policy, &reserved, exists);
+
+        assert_eq!(
+            resolve("/out/clip.mp4", CollisionPolicy::AutoIncrement).unwrap(),
+            Some("/out/clip (3).mp4".to_string())
+        );
+        assert_eq!(
+            resolve("/out/new.mp4", CollisionPolicy::AutoIncrement).unwrap(),
+            Some("/out/new.mp4".to_string())
+        );
+        assert_eq!(
+            resolve("/out/clip.mp4", CollisionPolicy::Skip).unwrap(),
+            None
+        );
+        assert!(resolve("/out/clip.mp4", CollisionPolicy::Fail).is_err());
+        assert_eq!(
+            resolve("/out/clip.mp4", CollisionPolicy::Overwrite).unwrap(),
+            Some("/out/clip.mp4".to_string())
+        );
+    }
+
+    #[test]
+    fn collision_policy_never_shares_a_running_output() {
+        let reserved: HashSet<String> = ["/out/clip.mp4".to_string()].into();
+        let nothing_on_disk = |_: &str| false;
+
+        for policy in [CollisionPolicy::Overwrite, CollisionPolicy::Fail] {
+            assert!(
+                resolve_output_collision("/out/clip.mp4", policy, &reserved, nothing_on_disk)
+                    .is_err()
+            );
+        }
+        assert_eq!(
+            resolve_output_collision(
+                "/out/clip.mp4",
+                CollisionPolicy::AutoIncrement,
+                &reserved,
+                nothing_on_disk
+            )
+            .unwrap(),
+            Some("/out/clip (1).mp4".to_string())
+        );
+    }
+
     #[test]
     fn test_custom_resolution_and_fps() {
         let mut config = sample_config("mp4");
@@ -227,6 +468,197 @@ mod tests {
         assert!(!args.iter().any(|a| a == "-crf"));
     }
 
+    #[test]
+    fn test_two_pass_first_pass_analyses_video_only() {
+        let mut config = sample_config("mp4");
+        config.video_bitrate_mode = "bitrate".into();
+        config.video_bitrate = "2500".into();
+        config.two_pass = true;
+
+        assert!(uses_two_pass(&config));
+        let [first, second] = build_two_pass_args("in.mp4", "out.mp4", &config, "/tmp/log");
+
+        assert!(contains_args(
+            &first,
+            &["-pass", "1", "-passlogfile", "/tmp/log"]
+        ));
+        assert!(contains_args(&first, &["-an", "-sn"]));
+        assert!(contains_args(&first, &["-f", "null"]));
+        assert!(!first.iter().any(|a| a == "out.mp4" || a == "-c:a"));
+
+        assert!(contains_args(
+            &second,
+            &["-pass", "2", "-passlogfile", "/tmp/log"]
+        ));
+        assert!(contains_arg_pair(&second, "-c:a", "aac"));
+        assert_eq!(second.last().map(String::as_str), Some("out.mp4"));
+    }
+
+    #[test]
+    fn test_two_pass_x265_uses_stats_param() {
+        let mut config = sample_config("mkv");
+        config.video_codec = "libx265".into();
+        config.video_bitrate_mode = "bitrate".into();
+        config.two_pass = true;
+
+        let [first, _] = build_two_pass_args("in.mp4", "out.mkv", &config, "C:\\tmp\\log");
+
+        assert!(contains_args(
+            &first,
+            &["-x265-params", "pass=1:stats=C\\:/tmp/log.log"]
+        ));
+        assert!(!first.iter().any(|a| a == "-pass"));
+    }
+
+    #[test]
+    fn test_two_pass_not_used_for_svtav1() {
+        let mut config = sample_config("mkv");
+        config.video_codec = "libsvtav1".into();
+        config.video_bitrate_mode = "bitrate".into();
+        config.two_pass = true;
+
+        assert!(!uses_two_pass(&config));
+        let args = build_ffmpeg_args("in.mp4", "out.mkv", &config);
+        assert!(contains_arg_pair(&args, "-b:v", "5000k"));
+        assert!(!args.iter().any(|a| a == "-pass" || a == "-passlogfile"));
+
+        let path = create_temp_input_file();
+        let result = validate_task_input(path.to_str().unwrap(), &config, None);
+        let _ = fs::remove_file(&path);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_two_pass_ignored_in_crf_mode() {
+        let mut config = sample_config("mp4");
+        config.two_pass = true;
+
+        assert!(!uses_two_pass(&config));
+        let args = build_ffmpeg_args("in.mp4", "out.mp4", &config);
+        assert!(!args.iter().any(|a| a == "-pass"));
+    }
+
+    #[test]
+    fn test_threads_limit_filters_and_encoder() {
+        let mut config = sample_config("mp4");
+        config.threads = Some(4);
+
+        let args = build_ffmpeg_args("in.mp4", "out.mp4", &config);
+        // Global filter options lead the command; -threads applies to the output.
+        assert_eq!(&args[..2], ["-filter_threads", "4"]);
+        assert!(contains_arg_pair(&args, "-filter_complex_threads", "4"));
+        let threads_idx = args.iter().position(|a| a == "-threads").unwrap();
+        assert_eq!(args[threads_idx + 1], "4");
+        assert!(threads_idx > args.iter().position(|a| a == "-i").unwrap());
+
+        let upscale = build_upscale_encode_args(
+            &PathBuf::from("/tmp/frame_upscale_test/output"),
+            "input.mp4",
+            "output.mp4",
+            30.0,
+            &config,
+            None,
+        );
+        assert_eq!(&upscale[..2], ["-filter_threads", "4"]);
+        assert!(contains_arg_pair(&upscale, "-threads", "4"));
+    }
+
+    #[test]
+    fn test_threads_unset_or_zero_leaves_ffmpeg_defaults() {
+        let mut config = sample_config("mp4");
+        for threads in [None, Some(0)] {
+            config.threads = threads;
+            let args = build_ffmpeg_args("in.mp4", "out.mp4", &config);
+            assert!(
+                !args
+                    .iter()
+                    .any(|a| a == "-threads" || a == "-filter_threads")
+            );
+        }
+    }
+
+    #[test]
+    fn test_process_priority_defaults_to_normal() {
+        let config: ConversionConfig = serde_json::from_value(serde_json::json!({
+            "container": "mp4",
+            "videoCodec": "libx264",
+            "videoBitrateMode": "crf",
+            "videoBitrate": "5000",
+            "audioCodec": "aac",
+            "audioBitrate": "128",
+            "audioChannels": "original",
+            "selectedAudioTracks": [],
+            "selectedSubtitleTracks": [],
+            "resolution": "original",
+            "scalingAlgorithm": "bicubic",
+            "fps": "original",
+            "crf": 23,
+            "preset": "medium",
+            "nvencSpatialAq": false,
+            "nvencTemporalAq": false,
+            "videotoolboxAllowSw": false
+        }))
+        .unwrap();
+        assert_eq!(config.process_priority, ProcessPriority::Normal);
+        assert_eq!(config.threads, None);
+
+        let low: ProcessPriority = serde_json::from_str("\"low\"").unwrap();
+        assert_eq!(low, ProcessPriority::Low);
+        // Normal never touches the process, so it succeeds for any pid.
+        assert!(set_process_priority(u32::MAX, ProcessPriority::Normal).is_ok());
+    }
+
+    #[test]
+    fn test_validate_rejects_two_pass_for_hardware_encoder() {
+        let mut config = sample_config("mp4");
+        config.video_codec = "h264_nvenc".into();
+        config.video_bitrate_mode = "bitrate".into();
+        config.two_pass = true;
+
+        let path = create_temp_input_file();
+        let result = validate_task_input(path.to_str().unwrap(), &config, None);
+        let _ = fs::remove_file(&path);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_target_size_resolves_bitrate_per_audio_stream() {
+        let mut config = sample_config("mp4");
+        config.video_bitrate_mode = "target_size".into();
+        config.target_size_mb = Some(25.0);
+        config.audio_bitrate = "128".into();
+
+        let resolved = resolve_target_size(&config, 60.0, 2).unwrap();
+        assert_eq!(resolved.video_bitrate, "3010");
+        assert!(uses_two_pass(&resolved));
+
+        let args = build_ffmpeg_args("in.mp4", "out.mp4", &resolved);
+        assert!(contains_arg_pair(&args, "-b:v", "3010k"));
+        assert!(!args.iter().any(|a| a == "-crf"));
+    }
+
+    #[test]
+    fn test_target_size_requires_known_duration() {
+        let mut config = sample_config("mp4");
+        config.video_bitrate_mode = "target_size".into();
+        config.target_size_mb = Some(8.0);
+
+        assert!(resolve_target_size(&config, 0.0, 1).is_err());
+    }
+
+    #[test]
+    fn test_validate_rejects_target_size_without_size() {
+        let mut config = sample_config("mp4");
+        config.video_bitrate_mode = "target_size".into();
+
+        let path = create_temp_input_file();
+        let result = validate_task_input(path.to_str().unwrap(), &config, None);
+        let _ = fs::remove_file(&path);
+
+        assert!(result.is_err());
+    }
+
     #[test]
     fn test_av1_codec() {
         let mut config = sample_config("mkv");
@@ -413,7 +845,7 @@ mod tests {
 
         let path = create_temp_input_file();
 
-        let result = validate_task_input(path.to_str().unwrap(), &config);
+        let result = validate_task_input(path.to_str().unwrap(), &config, None);
         let _ = fs::remove_file(&path);
 
         assert!(result.is_err());
@@ -426,7 +858,7 @@ mod tests {
         config.selected_audio_tracks = vec![0];
 
         let path = create_temp_input_file();
-        let result = validate_task_input(path.to_str().unwrap(), &config);
+        let result = validate_task_input(path.to_str().unwrap(), &config, None);
         let _ = fs::remove_file(&path);
 
         assert!(result.is_err());
@@ -439,7 +871,7 @@ mod tests {
         config.gif_dither = "jarvis".into();
 
         let path = create_temp_input_file();
-        let result = validate_task_input(path.to_str().unwrap(), &config);
+        let result = validate_task_input(path.to_str().unwrap(), &config, None);
         let _ = fs::remove_file(&path);
 
         assert!(result.is_err());
@@ -451,7 +883,7 @@ mod tests {
         config.ml_upscale = Some("esrgan-8x".into());
 
         let path = create_temp_input_file();
-        let result = validate_task_input(path.to_str().unwrap(), &config);
+        let result = validate_task_input(path.to_str().unwrap(), &config, None);
         let _ = fs::remove_file(&path);
 
         assert!(result.is_err());
@@ -464,7 +896,7 @@ mod tests {
         config.end_time = Some("00:01:00.000".into());
 
         let path = create_temp_input_file();
-        let result = validate_task_input(path.to_str().unwrap(), &config);
+        let result = validate_task_input(path.to_str().unwrap(), &config, None);
         let _ = fs::remove_file(&path);
 
         assert!(result.is_err());
@@ -477,58 +909,253 @@ mod tests {
         config.end_time = Some("00:02:00.000".into());
 
         let path = create_temp_input_file();
-        let result = validate_task_input(path.to_str().unwrap(), &config);
+        let result = validate_task_input(path.to_str().unwrap(), &config, None);
         let _ = fs::remove_file(&path);
 
         assert!(result.is_ok());
     }
 
+    fn segments(ranges: &[(&str, &str)]) -> Vec<TimeSegment> {
+        ranges
+            .iter()
+            .map(|(start, end)| TimeSegment {
+                start: (*start).into(),
+                end: (*end).into(),
+            })
+            .collect()
+    }
+
     #[test]
-    fn test_upscale_encode_uses_source_framerate_for_image_sequence() {
+    fn test_segments_build_trim_concat_graph() {
         let mut config = sample_config("mp4");
-        config.fps = "60".into();
+        config.segments = segments(&[("00:00:10", "00:00:20"), ("00:01:00", "00:01:30.5")]);
+        config.resolution = "720p".into();
+        config.audio_volume = 50.0;
 
-        let args = build_upscale_encode_args(
-            &PathBuf::from("/tmp/frame_upscale_test/output"),
-            "input.mp4",
-            "output.mp4",
-            23.976,
-            &config,
-            None,
-        );
+        let args = build_ffmpeg_args("in.mp4", "out.mp4", &config);
+        let graph = args
+            .iter()
+            .skip_while(|a| *a != "-filter_complex")
+            .nth(1)
+            .expect("filter graph");
 
-        let framerate_idx = args.iter().position(|arg| arg == "-framerate").unwrap();
-        assert_eq!(args[framerate_idx + 1], "23.976");
-        assert!(contains_arg_pair(&args, "-r", "60"));
+        assert!(graph.starts_with("[0:v:0]scale=-2:720"));
+        assert!(graph.contains(",split=2[vs0][vs1];"));
+        assert!(graph.contains("[vs1]trim=start=60.000:end=90.500,setpts=PTS-STARTPTS[v1]"));
+        assert!(graph.contains("[0:1]asplit=2[as0_0][as0_1]"));
+        assert!(graph.contains("[as0_0]atrim=start=10.000:end=20.000,asetpts=PTS-STARTPTS[a0_0]"));
+        assert!(graph.contains("[v0][a0_0][v1][a0_1]concat=n=2:v=1:a=1[vout][acat0]"));
+        assert!(graph.ends_with("[acat0]volume=0.50[aout0]"));
+        assert!(contains_arg_pair(&args, "-map", "[vout]"));
+        assert!(contains_arg_pair(&args, "-map", "[aout0]"));
+        assert!(!args.iter().any(|a| a == "-vf" || a == "-af" || a == "-ss"));
     }
 
     #[test]
-    fn test_upscale_encode_preserve_metadata_maps_from_source_input() {
-        let mut config = sample_config("mp4");
-        config.metadata.mode = MetadataMode::Preserve;
+    fn test_segments_in_copy_mode_use_concat_demuxer() {
+        let mut config = sample_config("mkv");
+        config.video_codec = "copy".into();
+        config.audio_codec = "copy".into();
+        config.segments = segments(&[("10", "20"), ("60", "90")]);
 
-        let args = build_upscale_encode_args(
-            &PathBuf::from("/tmp/frame_upscale_test/output"),
-            "input.mp4",
-            "output.mp4",
-            30.0,
-            &config,
-            None,
-        );
+        let args = build_ffmpeg_args("/tmp/segments.ffconcat", "out.mkv", &config);
 
-        assert!(contains_arg_pair(&args, "-map_metadata", "1"));
-        assert!(!contains_arg_pair(&args, "-map_metadata", "-1"));
+        assert!(contains_args(&args, &["-f", "concat", "-safe", "0"]));
+        assert!(contains_arg_pair(&args, "-i", "/tmp/segments.ffconcat"));
+        assert!(contains_arg_pair(&args, "-c:v", "copy"));
+        assert!(!args.iter().any(|a| a == "-filter_complex"));
+
+        let list = build_concat_list("/videos/it's.mkv", &[(10.0, 20.0), (60.0, 90.0)]);
+        assert_eq!(
+            list,
+            "ffconcat version 1.0\n\
+             file '/videos/it'\\''s.mkv'\ninpoint 10.000000\noutpoint 20.000000\n\
+             file '/videos/it'\\''s.mkv'\ninpoint 60.000000\noutpoint 90.000000\n"
+        );
     }
 
     #[test]
-    fn test_upscale_encode_replace_metadata_remains_clean_then_custom() {
-        let mut config = sample_config("mp4");
-        config.metadata.mode = MetadataMode::Replace;
-        config.metadata.title = Some("Upscaled".into());
+    fn test_validate_rejects_invalid_cut_lists() {
+        let path = create_temp_input_file();
+        let validate = |config: &ConversionConfig| {
+            validate_task_input(path.to_str().unwrap(), config, None).is_err()
+        };
 
-        let args = build_upscale_encode_args(
-            &PathBuf::from("/tmp/frame_upscale_test/output"),
-            "input.mp4",
+        let mut reversed = sample_config("mp4");
+        reversed.segments = segments(&[("00:00:20", "00:00:10")]);
+
+        let mut with_trim = sample_config("mp4");
+        with_trim.segments = segments(&[("10", "20")]);
+        with_trim.start_time = Some("5".into());
+
+        let mut soft_subtitles = sample_config("mkv");
+        soft_subtitles.segments = segments(&[("10", "20")]);
+        soft_subtitles.selected_subtitle_tracks = vec![2];
+
+        let mut valid = sample_config("mp4");
+        valid.segments = segments(&[("10", "20"), ("30", "40")]);
+
+        let results = [
+            validate(&reversed),
+            validate(&with_trim),
+            validate(&soft_subtitles),
+            validate(&valid),
+        ];
+        let _ = fs::remove_file(&path);
+
+        assert_eq!(results, [true, true, true, false]);
+    }
+
+    fn probe_with_codecs(video: &str, audio: &[&str]) -> ProbeMetadata {
+        ProbeMetadata {
+            video_codec: Some(video.into()),
+            audio_tracks: audio
+                .iter()
+                .enumerate()
+                .map(|(i, codec)| AudioTrack {
+                    index: i as u32 + 1,
+                    codec: (*codec).into(),
+                    channels: "2".into(),
+                    language: None,
+                    label: None,
+                    bitrate_kbps: None,
+                    sample_rate: None,
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_stream_copy_skips_encoder_options() {
+        let mut config = sample_config("mp4");
+        config.video_codec = "copy".into();
+        config.audio_codec = "copy".into();
+        config.hw_decode = true;
+
+        let args = build_ffmpeg_args("in.mkv", "out.mp4", &config);
+
+        assert!(contains_arg_pair(&args, "-c:v", "copy"));
+        assert!(contains_arg_pair(&args, "-c:a", "copy"));
+        assert!(
+            !args
+                .iter()
+                .any(|a| a == "-crf" || a == "-preset" || a == "-b:a")
+        );
+        assert!(!args.iter().any(|a| a == "-hwaccel" || a == "-vf"));
+    }
+
+    #[test]
+    fn test_stream_copy_trim_shifts_timestamps() {
+        let mut config = sample_config("mp4");
+        config.video_codec = "copy".into();
+        config.start_time = Some("4.004000".into());
+        config.end_time = Some("00:00:10".into());
+
+        let args = build_ffmpeg_args("in.mkv", "out.mp4", &config);
+
+        assert!(contains_arg_pair(&args, "-ss", "4.004000"));
+        assert!(contains_arg_pair(&args, "-t", "5.996"));
+        assert!(contains_arg_pair(&args, "-avoid_negative_ts", "make_zero"));
+
+        config.video_codec = "libx264".into();
+        let args = build_ffmpeg_args("in.mkv", "out.mp4", &config);
+        assert!(!args.iter().any(|a| a == "-avoid_negative_ts"));
+    }
+
+    #[test]
+    fn test_validate_accepts_compatible_stream_copy() {
+        let mut config = sample_config("mp4");
+        config.video_codec = "copy".into();
+        config.audio_codec = "copy".into();
+        let probe = probe_with_codecs("h264", &["aac", "opus"]);
+
+        let path = create_temp_input_file();
+        let result = validate_task_input(path.to_str().unwrap(), &config, Some(&probe));
+        let _ = fs::remove_file(&path);
+
+        assert!(result.is_ok());
+    }
+
+    #[test]
+    fn test_validate_rejects_incompatible_stream_copy() {
+        let mut config = sample_config("mp4");
+        config.audio_codec = "copy".into();
+        config.selected_audio_tracks = vec![];
+        let probe = probe_with_codecs("h264", &["aac", "pcm_s24le"]);
+
+        let path = create_temp_input_file();
+        let with_probe = validate_task_input(path.to_str().unwrap(), &config, Some(&probe));
+        config.selected_audio_tracks = vec![1];
+        let selected_only = validate_task_input(path.to_str().unwrap(), &config, Some(&probe));
+        let without_probe = validate_task_input(path.to_str().unwrap(), &config, None);
+        let _ = fs::remove_file(&path);
+
+        assert!(with_probe.is_err());
+        assert!(selected_only.is_ok());
+        assert!(without_probe.is_err());
+    }
+
+    #[test]
+    fn test_validate_rejects_video_copy_with_filters() {
+        let mut config = sample_config("mkv");
+        config.video_codec = "copy".into();
+        config.resolution = "720p".into();
+        let probe = probe_with_codecs("hevc", &[]);
+
+        let path = create_temp_input_file();
+        let result = validate_task_input(path.to_str().unwrap(), &config, Some(&probe));
+        let _ = fs::remove_file(&path);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_upscale_encode_uses_source_framerate_for_image_sequence() {
+        let mut config = sample_config("mp4");
+        config.fps = "60".into();
+
+        let args = build_upscale_encode_args(
+            &PathBuf::from("/tmp/frame_upscale_test/output"),
+            "input.mp4",
+            "output.mp4",
+            23.976,
+            &config,
+            None,
+        );
+
+        let framerate_idx = args.iter().position(|arg| arg == "-framerate").unwrap();
+        assert_eq!(args[framerate_idx + 1], "23.976");
+        assert!(contains_arg_pair(&args, "-r", "60"));
+    }
+
+    #[test]
+    fn test_upscale_encode_preserve_metadata_maps_from_source_input() {
+        let mut config = sample_config("mp4");
+        config.metadata.mode = MetadataMode::Preserve;
+
+        let args = build_upscale_encode_args(
+            &PathBuf::from("/tmp/frame_upscale_test/output"),
+            "input.mp4",
+            "output.mp4",
+            30.0,
+            &config,
+            None,
+        );
+
+        assert!(contains_arg_pair(&args, "-map_metadata", "1"));
+        assert!(!contains_arg_pair(&args, "-map_metadata", "-1"));
+    }
+
+    #[test]
+    fn test_upscale_encode_replace_metadata_remains_clean_then_custom() {
+        let mut config = sample_config("mp4");
+        config.metadata.mode = MetadataMode::Replace;
+        config.metadata.title = Some("Upscaled".into());
+
+        let args = build_upscale_encode_args(
+            &PathBuf::from("/tmp/frame_upscale_test/output"),
+            "input.mp4",
             "output.mp4",
             30.0,
             &config,
@@ -538,38 +1165,235 @@ mod tests {
         assert!(contains_arg_pair(&args, "-map_metadata", "-1"));
         assert!(contains_args(&args, &["-metadata", "title=Upscaled"]));
     }
+
+    fn output_spec(container: &str, codec: &str, resolution: &str) -> OutputSpec {
+        OutputSpec {
+            output_name: None,
+            container: container.into(),
+            video_codec: codec.into(),
+            resolution: resolution.into(),
+            video_bitrate_mode: None,
+            video_bitrate: None,
+            crf: None,
+            preset: None,
+            audio_codec: None,
+            audio_bitrate: None,
+        }
+    }
+
+    #[test]
+    fn test_multi_output_splits_one_decode() {
+        let mut config = sample_config("mp4");
+        config.resolution = "1080p".into();
+        config.rotation = "90".into();
+        let mut spec = output_spec("webm", "vp9", "480p");
+        spec.audio_codec = Some("libopus".into());
+        spec.crf = Some(33);
+        let outputs = vec![
+            ("out.mp4".to_string(), config.clone()),
+            (
+                "out_480p.webm".to_string(),
+                output_variant_config(&config, &spec),
+            ),
+        ];
+
+        let args = build_multi_output_args("in.mov", &outputs);
+
+        assert_eq!(args.iter().filter(|a| *a == "-i").count(), 1);
+        let graph_idx = args.iter().position(|a| a == "-filter_complex").unwrap();
+        let graph = &args[graph_idx + 1];
+        assert!(graph.starts_with("[0:v:0]transpose=1,split=2[s0][s1];"));
+        assert!(graph.contains("[s0]scale=-2:1080"));
+        assert!(graph.contains("[s1]scale=-2:480"));
+        assert!(!args.iter().any(|a| a == "-vf" || a == "-hwaccel"));
+
+        let first = args.iter().position(|a| a == "out.mp4").unwrap();
+        let second = args.iter().position(|a| a == "out_480p.webm").unwrap();
+        let (main_args, variant_args) = (&args[..first], &args[first..second]);
+        assert!(contains_arg_pair(main_args, "-map", "[v0]"));
+        assert!(contains_arg_pair(main_args, "-c:v", "libx264"));
+        assert!(contains_arg_pair(main_args, "-crf", "23"));
+        assert!(contains_arg_pair(variant_args, "-map", "[v1]"));
+        assert!(contains_arg_pair(variant_args, "-c:v", "vp9"));
+        assert!(contains_arg_pair(variant_args, "-crf", "33"));
+        assert!(contains_arg_pair(variant_args, "-c:a", "libopus"));
+        assert_eq!(args.last().unwrap(), "out_480p.webm");
+    }
+
+    #[test]
+    fn test_multi_output_keeps_unscaled_branch() {
+        let config = sample_config("mp4");
+        let outputs = vec![
+            ("a.mp4".to_string(), config.clone()),
+            (
+                "b.mp4".to_string(),
+                output_variant_config(&config, &output_spec("mp4", "libx264", "720p")),
+            ),
+        ];
+
+        let args = build_multi_output_args("in.mov", &outputs);
+        let graph_idx = args.iter().position(|a| a == "-filter_complex").unwrap();
+
+        assert_eq!(
+            args[graph_idx + 1],
+            "[0:v:0]split=2[s0][s1];[s0]null[v0];[s1]scale=-2:720:flags=bicubic[v1]"
+        );
+    }
+
+    #[test]
+    fn test_additional_output_path_follows_main_output() {
+        let config = sample_config("mp4");
+        let spec = output_spec("webm", "vp9", "720p");
+        let variant = output_variant_config(&config, &spec);
+
+        let path = additional_output_path("/src/clip.mov", "/out/clip.mp4", &spec, &variant);
+        assert_eq!(
+            PathBuf::from(path),
+            PathBuf::from("/out").join("clip_720p.webm")
+        );
+
+        let named = OutputSpec {
+            output_name: Some("preview".into()),
+            ..spec
+        };
+        let path = additional_output_path("/src/clip.mov", "/out/clip.mp4", &named, &variant);
+        assert_eq!(
+            PathBuf::from(path),
+            PathBuf::from("/out").join("preview.webm")
+        );
+    }
+
+    #[test]
+    fn test_validate_additional_outputs() {
+        let path = create_temp_input_file();
+        let input = path.to_str().unwrap().to_string();
+        let mut config = sample_config("mp4");
+        let mut webm = output_spec("webm", "vp9", "480p");
+        webm.audio_codec = Some("libopus".into());
+        config.additional_outputs = vec![webm];
+        let valid = validate_task_input(&input, &config, None);
+
+        config.additional_outputs = vec![output_spec("webm", "libx264", "480p")];
+        let incompatible = validate_task_input(&input, &config, None);
+
+        config.additional_outputs = vec![output_spec("gif", "gif", "480p")];
+        let gif = validate_task_input(&input, &config, None);
+
+        config.additional_outputs = vec![output_spec("mkv", "copy", "original")];
+        let copy = validate_task_input(&input, &config, None);
+
+        config.additional_outputs = vec![output_spec("mp4", "h264_nvenc", "720p")];
+        config.hw_fallback = true;
+        let hw_fallback = validate_task_input(&input, &config, None);
+        config.hw_fallback = false;
+
+        config.additional_outputs = vec![output_spec("mp4", "libx264", "720p")];
+        config.video_bitrate_mode = "bitrate".into();
+        config.two_pass = true;
+        let two_pass = validate_task_input(&input, &config, None);
+        let _ = fs::remove_file(&path);
+
+        assert!(valid.is_ok());
+        assert!(incompatible.is_err());
+        assert!(gif.is_err());
+        assert!(copy.is_err());
+        assert!(hw_fallback.is_err());
+        assert!(two_pass.is_err());
+    }
 }
 
 #[cfg(test)]
 mod parsing_tests {
-    use crate::conversion::utils::{DURATION_REGEX, FRAME_REGEX, TIME_REGEX, parse_time};
+    use crate::conversion::probe::parse_keyframe_packets;
+    use crate::conversion::progress::{FfmpegProgress, ProgressParser, estimate_eta};
+    use crate::conversion::utils::{DURATION_REGEX, FRAME_REGEX, parse_time};
+
+    fn feed(parser: &mut ProgressParser, block: &str) -> Option<FfmpegProgress> {
+        block
+            .lines()
+            .filter_map(|line| parser.push_line(line))
+            .last()
+    }
 
     #[test]
-    fn time_regex_extracts_progress_time() {
-        let ffmpeg_output = "frame=  120 fps= 30 q=23.0 size=    1024kB time=00:00:04.00 bitrate= 2097.2kbits/s speed=1.50x";
-        let caps = TIME_REGEX.captures(ffmpeg_output).unwrap();
-        assert_eq!(caps.get(1).unwrap().as_str(), "00:00:04.00");
+    fn progress_block_extracts_all_fields() {
+        let block = "frame=120\nfps=29.97\nstream_0_0_q=23.0\nbitrate=2097.2kbits/s\ntotal_size=1048576\nout_time_us=4000000\nout_time_ms=4000000\nout_time=00:00:04.000000\ndup_frames=0\ndrop_frames=0\nspeed=1.50x\nprogress=continue";
+        let mut parser = ProgressParser::default();
+        let update = feed(&mut parser, block).unwrap();
+
+        assert_eq!(update.frame, Some(120));
+        assert_eq!(update.fps, Some(29.97));
+        assert_eq!(update.bitrate_kbps, Some(2097.2));
+        assert_eq!(update.total_size, Some(1048576));
+        assert_eq!(update.out_time, Some(4.0));
+        assert_eq!(update.speed, Some(1.5));
+        assert!(!update.finished);
     }
 
     #[test]
-    fn time_regex_handles_long_duration() {
-        let output = "time=02:15:33.45 bitrate=1024kbits/s";
-        let caps = TIME_REGEX.captures(output).unwrap();
-        let time_str = caps.get(1).unwrap().as_str();
-        assert_eq!(time_str, "02:15:33.45");
-        assert_eq!(parse_time(time_str), Some(8133.45));
+    fn progress_block_handles_na_values() {
+        let block = "frame=0\nfps=0.00\nbitrate=N/A\ntotal_size=N/A\nout_time_us=N/A\nout_time_ms=N/A\nout_time=N/A\nspeed=N/A\nprogress=continue";
+        let mut parser = ProgressParser::default();
+        let update = feed(&mut parser, block).unwrap();
+
+        assert_eq!(update.bitrate_kbps, None);
+        assert_eq!(update.total_size, None);
+        assert_eq!(update.out_time, None);
+        assert_eq!(update.speed, None);
     }
 
     #[test]
-    fn time_regex_no_match_on_invalid() {
-        let invalid_outputs = ["frame=100 fps=30 q=23.0", "time=invalid", ""];
-        for output in invalid_outputs {
-            assert!(
-                TIME_REGEX.captures(output).is_none(),
-                "Should not match: {}",
-                output
-            );
-        }
+    fn progress_block_ignores_negative_start_time_and_marks_end() {
+        let mut parser = ProgressParser::default();
+        let first = feed(
+            &mut parser,
+            "out_time_us=-9223372036854775807\nout_time=-577014:32:22.775808\nprogress=continue",
+        )
+        .unwrap();
+        assert_eq!(first.out_time, None);
+
+        let last = feed(
+            &mut parser,
+            "out_time_us=8133450000\nspeed=4.2x\nprogress=end",
+        )
+        .unwrap();
+        assert_eq!(last.out_time, Some(8133.45));
+        assert!(last.finished);
+    }
+
+    #[test]
+    fn progress_block_falls_back_to_out_time_string() {
+        let mut parser = ProgressParser::default();
+        let update = feed(&mut parser, "out_time=02:15:33.450000\nprogress=continue").unwrap();
+        assert_eq!(update.out_time, Some(8133.45));
+    }
+
+    #[test]
+    fn progress_parser_ignores_non_progress_lines() {
+        let mut parser = ProgressParser::default();
+        assert!(parser.push_line("").is_none());
+        assert!(parser.push_line("Press [q] to stop").is_none());
+        assert!(parser.push_line("frame=10").is_none());
+    }
+
+    #[test]
+    fn eta_prefers_reported_speed() {
+        assert_eq!(estimate_eta(600.0, 300.0, Some(2.0), 10.0), Some(150.0));
+        assert_eq!(estimate_eta(600.0, 700.0, Some(2.0), 10.0), Some(0.0));
+    }
+
+    #[test]
+    fn eta_falls_back_to_elapsed_rate() {
+        assert_eq!(estimate_eta(600.0, 150.0, None, 50.0), Some(150.0));
+        assert_eq!(estimate_eta(600.0, 150.0, Some(0.0), 50.0), Some(150.0));
+        assert_eq!(estimate_eta(600.0, 0.0, None, 50.0), None);
+        assert_eq!(estimate_eta(0.0, 10.0, Some(1.0), 5.0), None);
+    }
+
+    #[test]
+    fn keyframe_packets_keep_only_keyframes_in_order() {
+        let output = "12.512000,__\n10.010000,K__\n14.014000,K_\nN/A,K__\n\n11.011000,___\n";
+        assert_eq!(parse_keyframe_packets(output), vec![10.01, 14.014]);
     }
 
     #[test]
@@ -634,7 +1458,8 @@ mod parsing_tests {
     #[test]
     fn progress_calculation_realistic() {
         let duration_line = "Duration: 00:10:00.00, start: 0.000000";
-        let progress_line = "frame=  900 fps=30 time=00:00:30.00 bitrate=2048kbits/s";
+        let progress_block =
+            "frame=900\nfps=30.00\nbitrate=2048.0kbits/s\nout_time_us=30000000\nprogress=continue";
 
         let total = DURATION_REGEX
             .captures(duration_line)
@@ -642,10 +1467,9 @@ mod parsing_tests {
             .and_then(|m| parse_time(m.as_str()))
             .unwrap();
 
-        let current = TIME_REGEX
-            .captures(progress_line)
-            .and_then(|c| c.get(1))
-            .and_then(|m| parse_time(m.as_str()))
+        let mut parser = ProgressParser::default();
+        let current = feed(&mut parser, progress_block)
+            .and_then(|update| update.out_time)
             .unwrap();
 
         let progress = (current / total * 100.0).min(100.0);
@@ -665,8 +1489,11 @@ mod utils_tests {
         is_video_only_container as is_video_only_container_rule,
     };
     use crate::conversion::utils::{
-        is_audio_only_container, is_nvenc_codec, is_videotoolbox_codec, map_nvenc_preset,
-        parse_frame_rate_string, parse_probe_bitrate, sanitize_external_tool_path,
+        civil_from_days, current_date, finish_partial_output, is_audio_only_container,
+        is_nvenc_codec, is_videotoolbox_codec, local_minutes_of_day, map_nvenc_preset,
+        map_nvenc_preset_to_software, nvenc_quality_to_crf, parse_frame_rate_string,
+        parse_probe_bitrate, partial_output_path, sanitize_external_tool_path, snap_to_keyframe,
+        target_video_bitrate_kbps,
     };
 
     #[test]
@@ -716,6 +1543,81 @@ mod utils_tests {
         assert_eq!(parse_probe_bitrate(Some("-1000")), None);
     }
 
+    #[test]
+    fn target_bitrate_subtracts_audio_and_overhead() {
+        assert_eq!(target_video_bitrate_kbps(25.0, 60.0, 128.0), Some(3138.0));
+        assert_eq!(target_video_bitrate_kbps(8.0, 10.0, 0.0), Some(6272.0));
+    }
+
+    #[test]
+    fn target_bitrate_rejects_impossible_targets() {
+        assert_eq!(target_video_bitrate_kbps(0.0, 60.0, 128.0), None);
+        assert_eq!(target_video_bitrate_kbps(25.0, 0.0, 128.0), None);
+        assert_eq!(target_video_bitrate_kbps(1.0, 3600.0, 128.0), None);
+    }
+
+    #[test]
+    fn civil_dates_from_unix_days() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(11_016), (2000, 2, 29));
+        assert_eq!(civil_from_days(20_743), (2026, 10, 17));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+    }
+
+    #[test]
+    fn current_date_is_a_local_calendar_date() {
+        let date = current_date();
+        let parts: Vec<u32> = date.split('-').map(|p| p.parse().unwrap()).collect();
+
+        assert_eq!(date.len(), 10);
+        assert!(parts[0] >= 2024);
+        assert!((1..=12).contains(&parts[1]) && (1..=31).contains(&parts[2]));
+        assert!(local_minutes_of_day() < 1440);
+    }
+
+    #[test]
+    fn keyframe_snap_prefers_preceding_keyframe() {
+        let keyframes = [0.0, 2.002, 4.004, 6.006];
+        assert_eq!(snap_to_keyframe(5.5, &keyframes), Some(4.004));
+        assert_eq!(snap_to_keyframe(4.004, &keyframes), Some(4.004));
+        assert_eq!(snap_to_keyframe(100.0, &keyframes), Some(6.006));
+        assert_eq!(snap_to_keyframe(1.0, &[2.0, 4.0]), None);
+    }
+
+    #[test]
+    fn partial_output_is_hidden_sibling_with_same_extension() {
+        let partial = partial_output_path("/videos/clip_converted.mp4", "task-1");
+        assert_eq!(
+            partial,
+            Path::new("/videos/.clip_converted.task-1.part.mp4")
+        );
+    }
+
+    #[test]
+    fn partial_output_replaces_destination_only_on_success() {
+        let dir = std::env::temp_dir().join(format!("frame-partial-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let output = dir.join("out.mp4");
+        let output_str = output.to_string_lossy().to_string();
+        std::fs::write(&output, b"previous").unwrap();
+
+        let partial = partial_output_path(&output_str, "a");
+        std::fs::write(&partial, b"truncated").unwrap();
+        finish_partial_output(&partial, &output_str, false).unwrap();
+        let kept = std::fs::read(&output).unwrap();
+        let partial_left = partial.exists();
+
+        std::fs::write(&partial, b"complete").unwrap();
+        finish_partial_output(&partial, &output_str, true).unwrap();
+        let replaced = std::fs::read(&output).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert_eq!(kept, b"previous");
+        assert!(!partial_left);
+        assert_eq!(replaced, b"complete");
+        assert!(finish_partial_output(&partial, &output_str, false).is_ok());
+    }
+
     #[test]
     fn audio_only_containers() {
         let audio_containers = ["mp3", "wav", "flac", "m4a", "aac", "MP3", "FLAC"];
@@ -780,6 +1682,26 @@ mod utils_tests {
         assert_eq!(map_nvenc_preset("unknown"), "medium");
     }
 
+    #[test]
+    fn nvenc_preset_to_software_mapping() {
+        assert_eq!(map_nvenc_preset_to_software("p1", "libx264"), "veryfast");
+        assert_eq!(map_nvenc_preset_to_software("p4", "libx265"), "medium");
+        assert_eq!(map_nvenc_preset_to_software("p7", "libx264"), "veryslow");
+        assert_eq!(map_nvenc_preset_to_software("slower", "libx264"), "slower");
+        assert_eq!(map_nvenc_preset_to_software("unknown", "libx264"), "medium");
+        assert_eq!(map_nvenc_preset_to_software("p1", "libsvtav1"), "12");
+        assert_eq!(map_nvenc_preset_to_software("medium", "libsvtav1"), "8");
+        assert_eq!(map_nvenc_preset_to_software("veryslow", "libsvtav1"), "4");
+    }
+
+    #[test]
+    fn nvenc_quality_maps_to_equivalent_crf() {
+        assert_eq!(nvenc_quality_to_crf(50, "libx264"), 27);
+        assert_eq!(nvenc_quality_to_crf(100, "libx265"), 2);
+        assert_eq!(nvenc_quality_to_crf(0, "libx264"), 51);
+        assert_eq!(nvenc_quality_to_crf(0, "libsvtav1"), 63);
+    }
+
     #[test]
     fn sanitize_external_tool_path_compatible_format() {
         #[cfg(windows)]
@@ -815,41 +1737,8 @@ mod scenario_tests {
 
     fn base_config() -> ConversionConfig {
         ConversionConfig {
-            container: "mp4".into(),
-            video_codec: "libx264".into(),
-            video_bitrate_mode: "crf".into(),
-            video_bitrate: "5000".into(),
-            audio_codec: "aac".into(),
-            audio_bitrate: "128".into(),
-            audio_channels: "original".into(),
-            audio_volume: 100.0,
-            selected_audio_tracks: vec![],
-            selected_subtitle_tracks: vec![],
-            subtitle_burn_path: None,
-            resolution: "original".into(),
-            custom_width: None,
-            custom_height: None,
             scaling_algorithm: "lanczos".into(),
-            fps: "original".into(),
-            crf: 23,
-            quality: 50,
-            preset: "medium".into(),
-            start_time: None,
-            end_time: None,
-            audio_normalize: false,
-            metadata: MetadataConfig::default(),
-            rotation: "0".into(),
-            flip_horizontal: false,
-            flip_vertical: false,
-            ml_upscale: None,
-            crop: None,
-            nvenc_spatial_aq: false,
-            nvenc_temporal_aq: false,
-            videotoolbox_allow_sw: false,
-            hw_decode: false,
-            gif_colors: 256,
-            gif_dither: "sierra2_4a".into(),
-            gif_loop: 0,
+            ..super::test_helpers::config()
         }
     }
 
@@ -1101,47 +1990,15 @@ mod scenario_tests {
 
 #[cfg(test)]
 mod hwaccel_tests {
-    use crate::conversion::args::build_ffmpeg_args;
-    use crate::conversion::types::{ConversionConfig, MetadataConfig};
+    use crate::conversion::args::{build_ffmpeg_args, software_fallback_config};
+    use crate::conversion::types::ConversionConfig;
     use crate::conversion::utils::get_hwaccel_args;
 
     fn hwaccel_config(codec: &str) -> ConversionConfig {
         ConversionConfig {
-            container: "mp4".into(),
             video_codec: codec.into(),
-            video_bitrate_mode: "crf".into(),
-            video_bitrate: "5000".into(),
-            audio_codec: "aac".into(),
-            audio_bitrate: "128".into(),
-            audio_channels: "original".into(),
-            audio_volume: 100.0,
-            selected_audio_tracks: vec![],
-            selected_subtitle_tracks: vec![],
-            subtitle_burn_path: None,
-            resolution: "original".into(),
-            custom_width: None,
-            custom_height: None,
-            scaling_algorithm: "bicubic".into(),
-            fps: "original".into(),
-            crf: 23,
-            quality: 50,
-            preset: "medium".into(),
-            start_time: None,
-            end_time: None,
-            audio_normalize: false,
-            metadata: MetadataConfig::default(),
-            rotation: "0".into(),
-            flip_horizontal: false,
-            flip_vertical: false,
-            ml_upscale: None,
-            crop: None,
-            nvenc_spatial_aq: false,
-            nvenc_temporal_aq: false,
-            videotoolbox_allow_sw: false,
             hw_decode: true,
-            gif_colors: 256,
-            gif_dither: "sierra2_4a".into(),
-            gif_loop: 0,
+            ..super::test_helpers::config()
         }
     }
 
@@ -1214,6 +2071,47 @@ mod hwaccel_tests {
         let args = build_ffmpeg_args("in.mp4", "out.mp4", &config);
         assert!(!args.contains(&"-hwaccel".to_string()));
     }
+
+    #[test]
+    fn software_fallback_maps_nvenc_to_software_encoder() {
+        let mut config = hwaccel_config("hevc_nvenc");
+        config.hw_fallback = true;
+        config.quality = 60;
+        config.preset = "p6".into();
+        config.nvenc_spatial_aq = true;
+
+        let fallback = software_fallback_config(&config).unwrap();
+        assert_eq!(fallback.video_codec, "libx265");
+        assert_eq!(fallback.crf, 22);
+        assert_eq!(fallback.preset, "slower");
+        assert!(!fallback.nvenc_spatial_aq);
+
+        let args = build_ffmpeg_args("in.mp4", "out.mp4", &fallback);
+        assert!(!args.contains(&"-hwaccel".to_string()));
+        assert!(args.windows(2).any(|w| w[0] == "-crf" && w[1] == "22"));
+    }
+
+    #[test]
+    fn software_fallback_scales_crf_for_svt_av1() {
+        let mut config = hwaccel_config("av1_nvenc");
+        config.hw_fallback = true;
+        config.quality = 60;
+
+        let fallback = software_fallback_config(&config).unwrap();
+        assert_eq!(fallback.video_codec, "libsvtav1");
+        assert_eq!(fallback.crf, 27);
+        assert_eq!(fallback.preset, "8");
+    }
+
+    #[test]
+    fn software_fallback_requires_opt_in_and_nvenc() {
+        let config = hwaccel_config("h264_nvenc");
+        assert!(software_fallback_config(&config).is_none());
+
+        let mut config = hwaccel_config("h264_videotoolbox");
+        config.hw_fallback = true;
+        assert!(software_fallback_config(&config).is_none());
+    }
 }
 
 #[cfg(test)]
@@ -1271,3 +2169,1219 @@ mod upscale_thread_tests {
         assert_eq!(extract_proc(&result), 2);
     }
 }
+
+#[cfg(test)]
+mod store_tests {
+    use super::test_helpers::task;
+    use crate::conversion::store::{JobStore, PersistedQueue};
+    use crate::conversion::types::{JobLink, QueueSchedule, StepOutput};
+    use std::collections::HashMap;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn temp_store_dir() -> std::path::PathBuf {
+        let ts = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        std::env::temp_dir().join(format!("frame-store-{}", ts))
+    }
+
+    #[test]
+    fn running_tasks_restore_as_interrupted() {
+        let persisted = PersistedQueue {
+            queued: vec![task("queued")],
+            running: vec![task("running"), task("already")],
+            interrupted: vec![task("already")],
+            schedule: QueueSchedule::default(),
+            blocked: vec![],
+            step_outputs: HashMap::new(),
+        };
+
+        let (queued, interrupted) = persisted.into_restored();
+
+        assert_eq!(queued.len(), 1);
+        assert_eq!(queued[0].id, "queued");
+        let ids: Vec<_> = interrupted.iter().map(|t| t.id.as_str()).collect();
+        assert_eq!(ids, vec!["already", "running"]);
+    }
+
+    #[test]
+    fn store_round_trips_full_config() {
+        let dir = temp_store_dir();
+        let store = JobStore::new(Some(dir.clone()));
+
+        let mut running = task("running");
+        running.config.start_time = Some("00:00:05".into());
+        running.output_name = Some("final".into());
+        let mut blocked = task("encode");
+        blocked.job = Some(JobLink {
+            job_id: "job".into(),
+            depends_on: vec!["remux".into()],
+            input_from: Some("remux".into()),
+            intermediate: false,
+            source_path: "/tmp/encode.mov".into(),
+        });
+
+        store
+            .save(&PersistedQueue {
+                queued: vec![task("a"), task("b")],
+                running: vec![running],
+                interrupted: vec![],
+                schedule: QueueSchedule {
+                    enabled: true,
+                    start: "23:30".into(),
+                    end: "07:00".into(),
+                },
+                blocked: vec![blocked],
+                step_outputs: HashMap::from([(
+                    "remux".to_string(),
+                    StepOutput {
+                        job_id: "job".into(),
+                        path: "/tmp/remux.mkv".into(),
+                        intermediate: true,
+                    },
+                )]),
+            })
+            .unwrap();
+
+        let loaded = store.load();
+        assert!(loaded.schedule.enabled);
+        assert_eq!(loaded.schedule.start, "23:30");
+        assert_eq!(loaded.blocked.len(), 1);
+        assert_eq!(
+            loaded.blocked[0]
+                .job
+                .as_ref()
+                .and_then(|job| job.input_from.as_deref()),
+            Some("remux")
+        );
+        assert!(loaded.step_outputs["remux"].intermediate);
+        let (queued, interrupted) = loaded.into_restored();
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert_eq!(
+            queued.iter().map(|t| t.id.as_str()).collect::<Vec<_>>(),
+            vec!["a", "b"]
+        );
+        assert_eq!(interrupted.len(), 1);
+        assert_eq!(interrupted[0].output_name.as_deref(), Some("final"));
+        assert_eq!(
+            interrupted[0].config.start_time.as_deref(),
+            Some("00:00:05")
+        );
+    }
+
+    #[test]
+    fn missing_or_corrupt_store_loads_empty() {
+        let dir = temp_store_dir();
+        let store = JobStore::new(Some(dir.clone()));
+        assert!(store.load().queued.is_empty());
+
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("conversion-queue.json"), b"{not json").unwrap();
+        let loaded = store.load();
+        let _ = std::fs::remove_dir_all(&dir);
+
+        assert!(loaded.queued.is_empty());
+        assert!(loaded.interrupted.is_empty());
+    }
+}
+
+#[cfg(test)]
+mod pause_all_tests {
+    use crate::conversion::manager::{
+        hold_tasks, release_held_tasks, resume_paused_tasks, suspend_tasks,
+    };
+    use std::collections::{HashMap, HashSet};
+
+    // Pid 0 stands in for a sidecar without a real process, so nothing is signalled.
+    fn active(ids: &[&str]) -> HashMap<String, Vec<u32>> {
+        ids.iter().map(|id| (id.to_string(), vec![0, 0])).collect()
+    }
+
+    #[test]
+    fn pause_all_marks_every_running_task() {
+        let mut paused = HashSet::from(["a".to_string()]);
+        suspend_tasks(&active(&["a", "b"]), &mut paused).unwrap();
+
+        assert_eq!(paused, HashSet::from(["a".to_string(), "b".to_string()]));
+    }
+
+    #[test]
+    fn resume_all_clears_only_active_tasks() {
+        let mut paused = HashSet::from(["a".to_string(), "finished".to_string()]);
+        resume_paused_tasks(&active(&["a", "b"]), &mut paused, &HashSet::new()).unwrap();
+
+        assert_eq!(paused, HashSet::from(["finished".to_string()]));
+    }
+
+    fn ids(list: &[&str]) -> HashSet<String> {
+        list.iter().map(|id| id.to_string()).collect()
+    }
+
+    #[test]
+    fn window_open_keeps_tasks_the_user_paused_while_closed() {
+        let tasks = active(&["a", "b"]);
+        let mut paused = HashSet::new();
+        let mut held = HashSet::new();
+        hold_tasks(&tasks, &mut paused, &mut held);
+        assert_eq!(held, ids(&["a", "b"]));
+
+        // Pausing a held task individually hands it over to the user.
+        held.remove("b");
+        release_held_tasks(&tasks, &mut paused, &mut held, false);
+
+        assert_eq!(paused, ids(&["b"]));
+        assert!(held.is_empty());
+    }
+
+    #[test]
+    fn resume_all_leaves_schedule_held_tasks_suspended() {
+        let tasks = active(&["a", "b"]);
+        let mut paused = ids(&["b"]);
+        let mut held = HashSet::new();
+        hold_tasks(&tasks, &mut paused, &mut held);
+        assert_eq!(held, ids(&["a"]));
+
+        resume_paused_tasks(&tasks, &mut paused, &held).unwrap();
+
+        assert_eq!(paused, ids(&["a"]));
+        release_held_tasks(&tasks, &mut paused, &mut held, false);
+        assert!(paused.is_empty());
+    }
+
+    #[test]
+    fn pause_all_then_resume_all_keeps_window_holds() {
+        let tasks = active(&["a", "b"]);
+        let mut paused = HashSet::new();
+        let mut held = HashSet::new();
+        hold_tasks(&tasks, &mut paused, &mut held);
+
+        suspend_tasks(&tasks, &mut paused).unwrap();
+        assert_eq!(held, ids(&["a", "b"]));
+
+        // The window is still closed, so resume_all leaves both suspended.
+        resume_paused_tasks(&tasks, &mut paused, &held).unwrap();
+        assert_eq!(paused, ids(&["a", "b"]));
+    }
+
+    #[test]
+    fn window_opening_during_pause_all_leaves_tasks_to_resume_all() {
+        let tasks = active(&["a"]);
+        let mut paused = HashSet::new();
+        let mut held = HashSet::new();
+        hold_tasks(&tasks, &mut paused, &mut held);
+        suspend_tasks(&tasks, &mut paused).unwrap();
+
+        release_held_tasks(&tasks, &mut paused, &mut held, true);
+        assert_eq!(paused, ids(&["a"]));
+        assert!(held.is_empty());
+
+        resume_paused_tasks(&tasks, &mut paused, &held).unwrap();
+        assert!(paused.is_empty());
+    }
+}
+
+#[cfg(test)]
+mod task_process_tests {
+    use crate::conversion::manager::TaskProcesses;
+    use std::collections::{HashMap, HashSet};
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn task_processes_track_every_stage() {
+        let active = Arc::new(Mutex::new(HashMap::from([("job".to_string(), Vec::new())])));
+        let cancelled = Arc::new(Mutex::new(HashSet::new()));
+        let paused = Arc::new(Mutex::new(HashSet::new()));
+        let processes = TaskProcesses::new("job", &active, &cancelled, &paused);
+
+        // Pid 0 stands in for a sidecar without a real process, so nothing is signalled.
+        processes.register(0).unwrap();
+        assert_eq!(active.lock().unwrap()["job"], vec![0]);
+
+        processes.unregister(0);
+        assert!(active.lock().unwrap()["job"].is_empty());
+    }
+
+    #[test]
+    fn task_processes_refuse_stages_after_cancel() {
+        let active = Arc::new(Mutex::new(HashMap::from([("job".to_string(), Vec::new())])));
+        let cancelled = Arc::new(Mutex::new(HashSet::from(["job".to_string()])));
+        let paused = Arc::new(Mutex::new(HashSet::new()));
+        let processes = TaskProcesses::new("job", &active, &cancelled, &paused);
+
+        assert!(processes.is_cancelled());
+        let err = processes.register(0).unwrap_err();
+        assert_eq!(err.kind(), "cancelled");
+        assert!(active.lock().unwrap()["job"].is_empty());
+    }
+}
+
+#[cfg(test)]
+mod retry_tests {
+    use crate::conversion::manager::{retry_delay_ms, validate_retry_policy};
+    use crate::conversion::types::RetryPolicy;
+
+    fn retry_policy(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            backoff_ms: 1_000,
+            backoff_multiplier: 2.0,
+            ..RetryPolicy::default()
+        }
+    }
+
+    #[test]
+    fn retry_delay_backs_off_until_attempts_run_out() {
+        let policy = retry_policy(4);
+        assert_eq!(retry_delay_ms(&policy, 1, "io"), Some(1_000));
+        assert_eq!(retry_delay_ms(&policy, 2, "io"), Some(2_000));
+        assert_eq!(retry_delay_ms(&policy, 3, "io"), Some(4_000));
+        assert_eq!(retry_delay_ms(&policy, 4, "io"), None);
+    }
+
+    #[test]
+    fn retry_delay_only_for_retryable_kinds() {
+        let policy = retry_policy(3);
+        assert!(retry_delay_ms(&policy, 1, "worker").is_some());
+        assert!(retry_delay_ms(&policy, 1, "invalid_input").is_none());
+        assert!(retry_delay_ms(&policy, 1, "disk_full").is_none());
+        assert!(retry_delay_ms(&RetryPolicy::default(), 1, "io").is_none());
+    }
+
+    #[test]
+    fn retry_delay_is_capped() {
+        let mut policy = retry_policy(100);
+        policy.backoff_multiplier = 10.0;
+        assert_eq!(retry_delay_ms(&policy, 20, "io"), Some(60 * 60 * 1000));
+    }
+
+    #[test]
+    fn retry_policy_rejects_zero_attempts() {
+        assert!(validate_retry_policy(&retry_policy(0)).is_err());
+        assert!(validate_retry_policy(&retry_policy(1)).is_ok());
+
+        let mut policy = retry_policy(3);
+        policy.backoff_multiplier = 0.5;
+        assert!(validate_retry_policy(&policy).is_err());
+    }
+
+    #[test]
+    fn retry_policy_deserializes_partial_override() {
+        let policy: RetryPolicy =
+            serde_json::from_value(serde_json::json!({ "maxAttempts": 3 })).unwrap();
+        assert_eq!(policy.max_attempts, 3);
+        assert_eq!(policy.backoff_ms, RetryPolicy::default().backoff_ms);
+        assert!(policy.retryable_kinds.iter().any(|k| k == "io"));
+    }
+}
+
+#[cfg(test)]
+mod queue_state_tests {
+    use super::test_helpers::task;
+    use crate::conversion::manager::task_state;
+    use crate::conversion::types::{JobLink, TaskState, TaskStatus};
+
+    #[test]
+    fn pending_task_state_carries_task_fields() {
+        let mut queued = task("a");
+        queued.priority = 3;
+        queued.output_name = Some("final".into());
+        queued.job = Some(JobLink {
+            job_id: "job".into(),
+            ..JobLink::default()
+        });
+
+        let state = task_state(&queued, TaskStatus::Queued);
+        assert_eq!(state.id, "a");
+        assert_eq!(state.priority, 3);
+        assert_eq!(state.job_id.as_deref(), Some("job"));
+        assert!(state.started_at.is_none() && state.pid.is_none());
+        assert_eq!(state.progress, 0.0);
+    }
+
+    #[test]
+    fn task_state_serializes_for_the_frontend() {
+        let state = TaskState {
+            position: Some(2),
+            ..task_state(&task("a"), TaskStatus::Queued)
+        };
+        let value = serde_json::to_value(&state).unwrap();
+
+        assert_eq!(value["status"], "queued");
+        assert_eq!(value["filePath"], "/tmp/a.mov");
+        assert_eq!(value["position"], 2);
+        assert!(value["startedAt"].is_null());
+
+        let retrying = serde_json::to_value(TaskStatus::Retrying).unwrap();
+        assert_eq!(retrying, "retrying");
+    }
+}
+
+#[cfg(test)]
+mod priority_tests {
+    use super::test_helpers::task;
+    use crate::conversion::manager::priority_insert_index;
+    use crate::conversion::types::ConversionTask;
+    use std::collections::VecDeque;
+
+    #[test]
+    fn priority_insert_keeps_arrival_order_within_priority() {
+        let mut queue: VecDeque<ConversionTask> = VecDeque::new();
+        for (id, priority) in [("a", 0), ("b", 5), ("c", 0)] {
+            let mut t = task(id);
+            t.priority = priority;
+            let index = priority_insert_index(&queue, priority);
+            queue.insert(index, t);
+        }
+
+        let order: Vec<_> = queue.iter().map(|t| t.id.as_str()).collect();
+        assert_eq!(order, vec!["b", "a", "c"]);
+
+        assert_eq!(priority_insert_index(&queue, 5), 1);
+        assert_eq!(priority_insert_index(&queue, 10), 0);
+        assert_eq!(priority_insert_index(&queue, -1), 3);
+    }
+
+    #[test]
+    fn task_without_priority_deserializes_as_default() {
+        let mut value = serde_json::to_value(task("legacy")).unwrap();
+        value.as_object_mut().unwrap().remove("priority");
+
+        let restored: ConversionTask = serde_json::from_value(value).unwrap();
+        assert_eq!(restored.priority, 0);
+    }
+}
+
+#[cfg(test)]
+mod output_claim_tests {
+    use super::test_helpers::task;
+    use crate::conversion::manager::claim_additional_outputs;
+    use crate::conversion::types::{CollisionPolicy, ConversionTask, OutputSpec};
+    use std::collections::HashSet;
+
+    fn preview_task(id: &str, policy: CollisionPolicy) -> ConversionTask {
+        let mut t = task(id);
+        t.collision_policy = policy;
+        t.config.additional_outputs = vec![OutputSpec {
+            output_name: Some("preview".into()),
+            container: "mp4".into(),
+            video_codec: "libx264".into(),
+            resolution: "720p".into(),
+            video_bitrate_mode: None,
+            video_bitrate: None,
+            crf: None,
+            preset: None,
+            audio_codec: None,
+            audio_bitrate: None,
+        }];
+        t
+    }
+
+    #[test]
+    fn concurrent_tasks_never_share_an_additional_output() {
+        let mut reserved = HashSet::new();
+        let first = preview_task("a", CollisionPolicy::AutoIncrement);
+        let claimed = claim_additional_outputs(&first, "/out/a.mp4", &mut reserved, |_| false);
+        assert_eq!(
+            claimed.unwrap(),
+            vec![(
+                "/out/preview.mp4".to_string(),
+                Some("/out/preview.mp4".to_string())
+            )]
+        );
+
+        // The second task starts while the first still holds its paths.
+        let second = preview_task("b", CollisionPolicy::AutoIncrement);
+        let claimed = claim_additional_outputs(&second, "/out/b.mp4", &mut reserved, |_| false);
+        assert_eq!(
+            claimed.unwrap()[0].1.as_deref(),
+            Some("/out/preview (1).mp4")
+        );
+        assert!(reserved.contains("/out/b.mp4") && reserved.contains("/out/preview (1).mp4"));
+
+        let overwrite = preview_task("c", CollisionPolicy::Overwrite);
+        assert!(
+            claim_additional_outputs(&overwrite, "/out/c.mp4", &mut reserved, |_| false).is_err()
+        );
+
+        let skip = preview_task("d", CollisionPolicy::Skip);
+        let claimed = claim_additional_outputs(&skip, "/out/d.mp4", &mut reserved, |_| false);
+        assert_eq!(claimed.unwrap()[0].1, None);
+    }
+
+    #[test]
+    fn sibling_outputs_of_one_task_get_distinct_paths() {
+        let mut twice = preview_task("a", CollisionPolicy::AutoIncrement);
+        let spec = twice.config.additional_outputs[0].clone();
+        twice.config.additional_outputs.push(spec);
+
+        let mut reserved = HashSet::new();
+        let claimed: Vec<_> =
+            claim_additional_outputs(&twice, "/out/a.mp4", &mut reserved, |_| false)
+                .unwrap()
+                .into_iter()
+                .map(|(_, path)| path.unwrap())
+                .collect();
+        assert_eq!(claimed, vec!["/out/preview.mp4", "/out/preview (1).mp4"]);
+    }
+}
+
+#[cfg(test)]
+mod cli_tests {
+    use std::path::PathBuf;
+
+    use crate::conversion::cli::{ConfigSource, find_preset, parse_cli_args, parse_config_json};
+    use crate::conversion::types::CollisionPolicy;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    const CONFIG_JSON: &str = r#"{
+        "container": "mkv",
+        "videoCodec": "libx264",
+        "videoBitrateMode": "crf",
+        "videoBitrate": "5000",
+        "audioCodec": "aac",
+        "audioBitrate": "128",
+        "audioChannels": "original",
+        "audioVolume": 100,
+        "audioNormalize": false,
+        "selectedAudioTracks": [],
+        "selectedSubtitleTracks": [],
+        "resolution": "original",
+        "customWidth": null,
+        "customHeight": null,
+        "scalingAlgorithm": "bicubic",
+        "fps": "original",
+        "crf": 20,
+        "quality": 50,
+        "preset": "medium",
+        "metadata": { "mode": "preserve" }
+    }"#;
+
+    #[test]
+    fn parses_input_config_and_output() {
+        let options = parse_cli_args(&args(&[
+            "in.mov",
+            "--config",
+            "job.json",
+            "-o",
+            "out.mkv",
+            "--if-exists",
+            "increment",
+            "-q",
+        ]))
+        .unwrap();
+
+        assert_eq!(options.input, "in.mov");
+        assert_eq!(
+            options.config,
+            ConfigSource::File(PathBuf::from("job.json"))
+        );
+        assert_eq!(options.output.as_deref(), Some("out.mkv"));
+        assert_eq!(options.collision_policy, CollisionPolicy::AutoIncrement);
+        assert!(options.quiet);
+    }
+
+    #[test]
+    fn rejects_incomplete_invocations() {
+        assert!(parse_cli_args(&args(&["in.mov"])).is_err());
+        assert!(parse_cli_args(&args(&["--preset", "web"])).is_err());
+        assert!(parse_cli_args(&args(&["in.mov", "--preset"])).is_err());
+        assert!(parse_cli_args(&args(&["in.mov", "--preset", "web", "--bogus"])).is_err());
+        assert!(parse_cli_args(&args(&["a.mov", "b.mov", "--preset", "web"])).is_err());
+        assert!(parse_cli_args(&args(&["in.mov", "-p", "web", "--if-exists", "ask"])).is_err());
+        assert!(parse_cli_args(&args(&["in.mov", "-p", "web", "-o", "x.mp4", "-n", "x"])).is_err());
+    }
+
+    #[test]
+    fn config_json_accepts_bare_config_and_preset_wrapper() {
+        let (bare, name) = parse_config_json(CONFIG_JSON).unwrap();
+        assert_eq!(bare.container, "mkv");
+        assert_eq!(name, None);
+
+        let wrapped = format!(r#"{{"id": "x", "name": "X", "config": {}}}"#, CONFIG_JSON);
+        let (from_preset, name) = parse_config_json(&wrapped).unwrap();
+        assert_eq!(from_preset.video_codec, "libx264");
+        assert_eq!(from_preset.crf, 20);
+        assert_eq!(name.as_deref(), Some("X"));
+    }
+
+    #[test]
+    fn preset_lookup_matches_id_or_name_case_insensitively() {
+        let store = format!(
+            r#"{{"presets": [{{"id": "custom-1", "name": "Web Upload", "config": {}}}]}}"#,
+            CONFIG_JSON
+        );
+
+        let (_, name) = find_preset(&store, "custom-1").unwrap();
+        assert_eq!(name.as_deref(), Some("Web Upload"));
+        assert!(find_preset(&store, "web upload").is_ok());
+        assert!(find_preset(&store, "archive").is_err());
+    }
+}
+
+#[cfg(test)]
+mod verify_tests {
+    use super::test_helpers;
+    use super::test_helpers::media;
+    use crate::conversion::types::{ConversionConfig, VerificationStatus};
+    use crate::conversion::verify::{check_verification, verify_output};
+
+    fn config(container: &str) -> ConversionConfig {
+        ConversionConfig {
+            container: container.into(),
+            ..test_helpers::config()
+        }
+    }
+
+    #[test]
+    fn matching_output_passes() {
+        let source = media(true, 2, "120.0");
+        let result = verify_output(
+            &config("mp4"),
+            Some(&source),
+            120.0,
+            &media(true, 2, "120.4"),
+        );
+
+        assert_eq!(result.status, VerificationStatus::Passed);
+        assert!(result.issues.is_empty());
+    }
+
+    #[test]
+    fn missing_audio_or_short_output_fails() {
+        let source = media(true, 1, "120.0");
+
+        let no_audio = verify_output(
+            &config("mp4"),
+            Some(&source),
+            120.0,
+            &media(true, 0, "120.0"),
+        );
+        assert_eq!(no_audio.status, VerificationStatus::Failed);
+
+        let short = verify_output(
+            &config("mp4"),
+            Some(&source),
+            120.0,
+            &media(true, 1, "61.0"),
+        );
+        assert_eq!(short.status, VerificationStatus::Failed);
+
+        let no_video = verify_output(
+            &config("mkv"),
+            Some(&source),
+            120.0,
+            &media(false, 1, "120.0"),
+        );
+        assert_eq!(no_video.status, VerificationStatus::Failed);
+    }
+
+    #[test]
+    fn trim_window_and_track_selection_are_respected() {
+        let source = media(true, 3, "600.0");
+        let mut cfg = config("mkv");
+        cfg.selected_audio_tracks = vec![2];
+
+        let trimmed = verify_output(&cfg, Some(&source), 30.0, &media(true, 1, "30.5"));
+        assert_eq!(trimmed.status, VerificationStatus::Passed);
+
+        let extra_tracks = verify_output(&cfg, Some(&source), 30.0, &media(true, 3, "34.0"));
+        assert_eq!(extra_tracks.status, VerificationStatus::Warning);
+        assert_eq!(extra_tracks.issues.len(), 2);
+    }
+
+    #[test]
+    fn gif_output_expects_no_audio() {
+        let source = media(true, 1, "10.0");
+        let result = verify_output(&config("gif"), Some(&source), 10.0, &media(true, 0, "10.0"));
+        assert_eq!(result.status, VerificationStatus::Passed);
+    }
+
+    #[test]
+    fn failed_verification_fails_the_task() {
+        let missing_video = verify_output(&config("mp4"), None, 10.0, &media(false, 1, "10.0"));
+        let err = check_verification("/out/clip.mp4", &missing_video).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("/out/clip.mp4: Output has no video stream")
+        );
+
+        let source = media(true, 1, "10.0");
+        let extra_track =
+            verify_output(&config("mp4"), Some(&source), 10.0, &media(true, 2, "10.0"));
+        assert_eq!(extra_track.status, VerificationStatus::Warning);
+        assert!(check_verification("/out/clip.mp4", &extra_track).is_ok());
+    }
+}
+
+#[cfg(test)]
+mod stop_tests {
+    use super::test_helpers;
+    use super::test_helpers::media;
+    use crate::conversion::types::VerificationStatus;
+    use crate::conversion::verify::verify_output;
+    use crate::conversion::worker::completed_payload;
+
+    #[test]
+    fn stopped_output_is_verified_without_its_duration() {
+        let config = test_helpers::config();
+        let source = media(true, 1, "120.0");
+
+        // The worker passes no expected duration once a stop was requested.
+        let stopped = verify_output(&config, Some(&source), 0.0, &media(true, 1, "31.5"));
+        assert_eq!(stopped.status, VerificationStatus::Passed);
+
+        let finished = verify_output(&config, Some(&source), 120.0, &media(true, 1, "31.5"));
+        assert_eq!(finished.status, VerificationStatus::Failed);
+    }
+
+    #[test]
+    fn partial_fields_are_only_sent_for_stopped_tasks() {
+        let config = test_helpers::config();
+        let mut payload =
+            completed_payload("a".into(), "/tmp/frame-missing.mp4".into(), &config, None);
+        let value = serde_json::to_value(&payload).unwrap();
+        assert!(value.get("partial").is_none());
+        assert!(value.get("encoded_duration").is_none());
+
+        payload.partial = true;
+        payload.encoded_duration = Some(31.5);
+        let value = serde_json::to_value(&payload).unwrap();
+        assert_eq!(value["partial"], true);
+        assert_eq!(value["encoded_duration"], 31.5);
+    }
+}
+
+#[cfg(test)]
+mod diagnostics_tests {
+    use crate::conversion::diagnostics::{StderrTail, classify_ffmpeg_failure};
+
+    fn tail(lines: &[&str]) -> StderrTail {
+        let mut tail = StderrTail::default();
+        for line in lines {
+            tail.push(line);
+        }
+        tail
+    }
+
+    fn classify(lines: &[&str]) -> (&'static str, Option<String>) {
+        let err = classify_ffmpeg_failure(&tail(lines), Some(1));
+        (err.kind(), err.excerpt().map(str::to_string))
+    }
+
+    #[test]
+    fn known_failures_map_to_error_kinds() {
+        let cases = [
+            (
+                "[h264_nvenc @ 0x1] OpenEncodeSessionEx failed: out of memory (10): (no details)",
+                "nvenc_session_limit",
+            ),
+            ("Unknown encoder 'libsvtav1'", "encoder_unavailable"),
+            (
+                "[hevc_nvenc @ 0x1] Cannot load libnvidia-encode.so.1",
+                "encoder_unavailable",
+            ),
+            (
+                "av_interleaved_write_frame(): No space left on device",
+                "disk_full",
+            ),
+            ("/out/clip.mp4: Permission denied", "permission_denied"),
+            (
+                "in.mp4: Invalid data found when processing input",
+                "invalid_data",
+            ),
+            (
+                "[mp4 @ 0x1] Could not find tag for codec pcm_s16le in stream #1, codec not currently supported in container",
+                "unsupported_codec",
+            ),
+        ];
+
+        for (line, kind) in cases {
+            let (actual, excerpt) = classify(&["Stream mapping:", line, "Conversion failed!"]);
+            assert_eq!(actual, kind, "{}", line);
+            assert_eq!(excerpt.as_deref(), Some(line));
+        }
+    }
+
+    #[test]
+    fn session_limit_wins_over_generic_encoder_errors() {
+        let (kind, _) = classify(&[
+            "[h264_nvenc @ 0x1] OpenEncodeSessionEx failed: incompatible client key (21): (no details)",
+            "[h264_nvenc @ 0x1] No capable devices found",
+            "Error while opening encoder for output stream #0:0",
+        ]);
+        assert_eq!(kind, "nvenc_session_limit");
+    }
+
+    #[test]
+    fn unknown_failures_keep_exit_code_and_last_line() {
+        let err =
+            classify_ffmpeg_failure(&tail(&["something odd", "Conversion failed!"]), Some(187));
+        assert_eq!(err.kind(), "worker");
+        assert!(err.excerpt().is_none());
+        assert_eq!(
+            err.to_string(),
+            "Worker process error: Process terminated with code Some(187): Conversion failed!"
+        );
+    }
+
+    #[test]
+    fn tail_keeps_only_the_most_recent_lines() {
+        let mut tail = StderrTail::with_capacity(2);
+        for line in ["one", "", "two", "three"] {
+            tail.push(line);
+        }
+        assert_eq!(tail.join(), "two\nthree");
+    }
+}
+
+#[cfg(test)]
+mod scheduler_tests {
+    use super::test_helpers;
+    use crate::conversion::manager::{validate_queue_schedule, validate_scheduler_config};
+    use crate::conversion::scheduler::{
+        fits_budget, minutes_until_window_change, parse_clock, task_cost, window_is_open,
+    };
+    use crate::conversion::types::{
+        ConversionConfig, OutputSpec, QueueSchedule, SchedulerConfig, SchedulerMode, TaskCost,
+    };
+
+    fn config(codec: &str, resolution: &str) -> ConversionConfig {
+        ConversionConfig {
+            video_codec: codec.into(),
+            resolution: resolution.into(),
+            ..test_helpers::config()
+        }
+    }
+
+    fn adaptive(cpu_budget: f64, gpu_budget: f64) -> SchedulerConfig {
+        SchedulerConfig {
+            mode: SchedulerMode::Adaptive,
+            cpu_budget,
+            gpu_budget,
+        }
+    }
+
+    #[test]
+    fn software_cost_scales_with_codec_and_resolution() {
+        let x264 = task_cost(&config("libx264", "1080p"));
+        assert_eq!(x264, TaskCost { cpu: 1.0, gpu: 0.0 });
+
+        let x265 = task_cost(&config("libx265", "1080p"));
+        assert_eq!(x265.cpu, 2.0);
+
+        let small = task_cost(&config("libx264", "480p"));
+        assert_eq!(small.cpu, 0.25);
+
+        // Keeping the source resolution assumes 1080p until the input is probed.
+        assert_eq!(task_cost(&config("libx264", "original")).cpu, 1.0);
+    }
+
+    #[test]
+    fn additional_outputs_add_their_encoder_cost() {
+        let mut multi = config("libx264", "1080p");
+        multi.additional_outputs = vec![OutputSpec {
+            output_name: None,
+            container: "mp4".into(),
+            video_codec: "h264_nvenc".into(),
+            resolution: "720p".into(),
+            video_bitrate_mode: None,
+            video_bitrate: None,
+            crf: None,
+            preset: None,
+            audio_codec: None,
+            audio_bitrate: None,
+        }];
+
+        assert_eq!(
+            task_cost(&multi),
+            TaskCost {
+                cpu: 1.25,
+                gpu: 1.0
+            }
+        );
+    }
+
+    #[test]
+    fn hardware_and_copy_tasks_stay_off_the_cpu_budget() {
+        let nvenc = task_cost(&config("h264_nvenc", "1080p"));
+        assert_eq!(
+            nvenc,
+            TaskCost {
+                cpu: 0.25,
+                gpu: 1.0
+            }
+        );
+
+        let copy = task_cost(&config("copy", "original"));
+        assert_eq!(copy.gpu, 0.0);
+        assert!(copy.cpu < 0.25);
+
+        let mut audio = config("libx264", "original");
+        audio.container = "mp3".into();
+        assert_eq!(
+            task_cost(&audio),
+            TaskCost {
+                cpu: 0.25,
+                gpu: 0.0
+            }
+        );
+    }
+
+    #[test]
+    fn upscaling_adds_gpu_cost() {
+        let mut upscale = config("libx264", "original");
+        upscale.ml_upscale = Some("esrgan-2x".into());
+        let cost = task_cost(&upscale);
+        assert_eq!(cost.gpu, 2.0);
+        assert!(cost.cpu > 1.0);
+
+        upscale.ml_upscale = Some("none".into());
+        assert_eq!(task_cost(&upscale).gpu, 0.0);
+    }
+
+    #[test]
+    fn budgets_gate_cpu_and_gpu_separately() {
+        let scheduler = adaptive(2.0, 1.0);
+        let software = TaskCost { cpu: 1.0, gpu: 0.0 };
+        let hardware = TaskCost {
+            cpu: 0.25,
+            gpu: 1.0,
+        };
+
+        assert!(fits_budget(&scheduler, software, software, 1));
+        assert!(!fits_budget(
+            &scheduler,
+            TaskCost { cpu: 2.0, gpu: 0.0 },
+            software,
+            2
+        ));
+        // A full CPU budget still leaves room for a hardware encode...
+        assert!(!fits_budget(
+            &scheduler,
+            TaskCost { cpu: 2.0, gpu: 0.0 },
+            hardware,
+            2
+        ));
+        assert!(fits_budget(
+            &scheduler,
+            TaskCost { cpu: 1.5, gpu: 0.0 },
+            hardware,
+            2
+        ));
+        // ...but not a second NVENC session beyond the GPU budget.
+        assert!(!fits_budget(&scheduler, hardware, hardware, 1));
+    }
+
+    #[test]
+    fn oversized_task_runs_alone() {
+        let scheduler = adaptive(1.0, 1.0);
+        let huge = TaskCost { cpu: 4.0, gpu: 2.0 };
+        assert!(fits_budget(&scheduler, TaskCost::default(), huge, 0));
+        assert!(!fits_budget(&scheduler, TaskCost::default(), huge, 1));
+    }
+
+    #[test]
+    fn scheduler_config_validation_and_defaults() {
+        let defaults = SchedulerConfig::default();
+        assert_eq!(defaults.mode, SchedulerMode::Fixed);
+        assert!(defaults.cpu_budget >= 1.0);
+        assert!(validate_scheduler_config(&defaults).is_ok());
+
+        assert!(validate_scheduler_config(&adaptive(0.0, 1.0)).is_err());
+        assert!(validate_scheduler_config(&adaptive(1.0, f64::NAN)).is_err());
+
+        let partial: SchedulerConfig = serde_json::from_str(r#"{"mode":"adaptive"}"#).unwrap();
+        assert_eq!(partial.mode, SchedulerMode::Adaptive);
+        assert_eq!(partial.gpu_budget, 2.0);
+    }
+
+    fn window(start: &str, end: &str) -> QueueSchedule {
+        QueueSchedule {
+            enabled: true,
+            start: start.into(),
+            end: end.into(),
+        }
+    }
+
+    #[test]
+    fn clock_parsing_accepts_only_valid_times() {
+        assert_eq!(parse_clock("22:00"), Some(22 * 60));
+        assert_eq!(parse_clock("6:05"), Some(6 * 60 + 5));
+        assert_eq!(parse_clock("24:00"), None);
+        assert_eq!(parse_clock("12:60"), None);
+        assert_eq!(parse_clock("noon"), None);
+
+        assert!(validate_queue_schedule(&window("22:00", "06:00")).is_ok());
+        assert!(validate_queue_schedule(&window("22:00", "6pm")).is_err());
+    }
+
+    #[test]
+    fn overnight_window_spans_midnight() {
+        let schedule = window("22:00", "06:00");
+        assert!(window_is_open(&schedule, 23 * 60));
+        assert!(window_is_open(&schedule, 60));
+        assert!(!window_is_open(&schedule, 6 * 60));
+        assert!(!window_is_open(&schedule, 12 * 60));
+        assert!(window_is_open(&schedule, 22 * 60));
+
+        assert_eq!(minutes_until_window_change(&schedule, 21 * 60), Some(60));
+        assert_eq!(
+            minutes_until_window_change(&schedule, 23 * 60),
+            Some(7 * 60)
+        );
+    }
+
+    #[test]
+    fn daytime_window_and_disabled_schedule() {
+        let schedule = window("09:00", "17:30");
+        assert!(window_is_open(&schedule, 9 * 60));
+        assert!(!window_is_open(&schedule, 17 * 60 + 30));
+        assert!(!window_is_open(&schedule, 8 * 60));
+
+        let mut disabled = schedule.clone();
+        disabled.enabled = false;
+        assert!(window_is_open(&disabled, 8 * 60));
+        assert_eq!(minutes_until_window_change(&disabled, 8 * 60), None);
+
+        // Equal bounds mean no restriction rather than an empty window.
+        assert!(window_is_open(&window("08:00", "08:00"), 3 * 60));
+    }
+}
+
+#[cfg(test)]
+mod pipeline_tests {
+    use super::test_helpers::task;
+    use crate::conversion::args::resolve_output_path;
+    use crate::conversion::pipeline::{
+        collect_job_outputs, is_ready, output_source, resolve_step_input, step_output,
+        take_dependents, unused_intermediates, validate_pipeline,
+    };
+    use crate::conversion::types::{ConversionTask, JobLink, PipelineStep, StepOutput};
+    use std::collections::HashMap;
+
+    fn step(id: &str, depends_on: &[&str], input_from: Option<&str>) -> PipelineStep {
+        PipelineStep {
+            id: id.into(),
+            config: task(id).config,
+            output_name: None,
+            depends_on: depends_on.iter().map(|d| d.to_string()).collect(),
+            input_from: input_from.map(Into::into),
+            intermediate: false,
+        }
+    }
+
+    fn step_task(id: &str, depends_on: &[&str], input_from: Option<&str>) -> ConversionTask {
+        let mut step_task = task(id);
+        step_task.file_path = "/videos/source.mov".into();
+        step_task.job = Some(JobLink {
+            job_id: "job".into(),
+            depends_on: depends_on.iter().map(|d| d.to_string()).collect(),
+            input_from: input_from.map(Into::into),
+            intermediate: false,
+            source_path: "/videos/source.mov".into(),
+        });
+        step_task
+    }
+
+    fn output(path: &str, intermediate: bool) -> StepOutput {
+        StepOutput {
+            job_id: "job".into(),
+            path: path.into(),
+            intermediate,
+        }
+    }
+
+    #[test]
+    fn accepts_a_chain_that_fans_out() {
+        let steps = [
+            step("remux", &[], None),
+            step("normalize", &["remux"], Some("remux")),
+            step("hd", &["normalize"], Some("normalize")),
+            step("sd", &["normalize"], Some("normalize")),
+            step("thumbs", &["hd", "sd"], Some("hd")),
+        ];
+        assert!(validate_pipeline(&steps).is_ok());
+    }
+
+    #[test]
+    fn rejects_forward_duplicate_and_undeclared_inputs() {
+        assert!(validate_pipeline(&[]).is_err());
+
+        let forward = [step("a", &["b"], None), step("b", &[], None)];
+        assert!(validate_pipeline(&forward).is_err());
+
+        let cycle = [step("a", &["a"], None)];
+        assert!(validate_pipeline(&cycle).is_err());
+
+        let duplicate = [step("a", &[], None), step("a", &[], None)];
+        assert!(validate_pipeline(&duplicate).is_err());
+
+        let undeclared = [step("a", &[], None), step("b", &[], Some("a"))];
+        let err = validate_pipeline(&undeclared).unwrap_err();
+        assert!(err.to_string().contains("without depending on it"));
+    }
+
+    #[test]
+    fn step_waits_for_every_dependency_and_reads_its_input() {
+        let mut outputs = HashMap::new();
+        let mut thumbs = step_task("thumbs", &["hd", "sd"], Some("hd"));
+        assert!(is_ready(&task("plain"), &outputs));
+        assert!(!is_ready(&thumbs, &outputs));
+
+        outputs.insert("hd".to_string(), output("/out/hd.mp4", false));
+        assert!(!is_ready(&thumbs, &outputs));
+
+        outputs.insert("sd".to_string(), output("/out/sd.mp4", false));
+        assert!(is_ready(&thumbs, &outputs));
+
+        resolve_step_input(&mut thumbs, &outputs);
+        assert_eq!(thumbs.file_path, "/out/hd.mp4");
+
+        let mut from_source = step_task("remux", &[], None);
+        resolve_step_input(&mut from_source, &outputs);
+        assert_eq!(from_source.file_path, "/videos/source.mov");
+    }
+
+    #[test]
+    fn intermediates_are_released_once_no_pending_step_reads_them() {
+        let outputs = HashMap::from([
+            ("remux".to_string(), output("/tmp/remux.mkv", true)),
+            ("normalize".to_string(), output("/tmp/normalized.mkv", true)),
+            ("hd".to_string(), output("/out/hd.mp4", false)),
+        ]);
+
+        let sd = step_task("sd", &["normalize"], Some("normalize"));
+        assert_eq!(
+            unused_intermediates("job", &outputs, [&sd].into_iter()),
+            vec!["remux".to_string()]
+        );
+        assert_eq!(
+            unused_intermediates("job", &outputs, std::iter::empty()),
+            vec!["normalize".to_string(), "remux".to_string()]
+        );
+        assert!(unused_intermediates("other", &outputs, std::iter::empty()).is_empty());
+    }
+
+    #[test]
+    fn final_step_output_survives_intermediate_cleanup() {
+        let dir = std::env::temp_dir().join(format!("frame-pipeline-{}", std::process::id()));
+        let temp_dir = dir.join("job");
+        std::fs::create_dir_all(&temp_dir).unwrap();
+
+        let intermediate = temp_dir.join("source.mkv");
+        std::fs::write(&intermediate, b"remux").unwrap();
+        let mut outputs = HashMap::from([(
+            "remux".to_string(),
+            output(&intermediate.to_string_lossy(), true),
+        )]);
+
+        // The final step reads the intermediate but has no output directory.
+        let mut encode = step_task("encode", &["remux"], Some("remux"));
+        encode.job.as_mut().unwrap().source_path = dir.join("source.mov").to_string_lossy().into();
+        resolve_step_input(&mut encode, &outputs);
+        assert_eq!(encode.file_path, intermediate.to_string_lossy());
+
+        let final_path = resolve_output_path(output_source(&encode), &encode.config, None);
+        assert!(!std::path::Path::new(&final_path).starts_with(&temp_dir));
+        std::fs::write(&final_path, b"final").unwrap();
+        outputs.insert("encode".to_string(), output(&final_path, false));
+
+        collect_job_outputs("job", &mut outputs, &[], &temp_dir);
+        assert!(!intermediate.exists());
+        assert!(!temp_dir.exists());
+        assert!(std::path::Path::new(&final_path).exists());
+        assert!(outputs.is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn job_cleanup_only_removes_recorded_intermediates() {
+        let temp_dir =
+            std::env::temp_dir().join(format!("frame-pipeline-keep-{}", std::process::id()));
+        std::fs::create_dir_all(&temp_dir).unwrap();
+        let intermediate = temp_dir.join("normalized.mkv");
+        let deliverable = temp_dir.join("final.mp4");
+        std::fs::write(&intermediate, b"normalize").unwrap();
+        std::fs::write(&deliverable, b"final").unwrap();
+
+        let mut outputs = HashMap::from([
+            (
+                "normalize".to_string(),
+                output(&intermediate.to_string_lossy(), true),
+            ),
+            (
+                "encode".to_string(),
+                output(&deliverable.to_string_lossy(), false),
+            ),
+        ]);
+
+        let pending = [step_task("encode", &["normalize"], Some("normalize"))];
+        collect_job_outputs("job", &mut outputs, &pending, &temp_dir);
+        assert!(intermediate.exists());
+        assert_eq!(outputs.len(), 2);
+
+        collect_job_outputs("job", &mut outputs, &[], &temp_dir);
+        assert!(!intermediate.exists());
+        assert!(deliverable.exists());
+        assert!(outputs.is_empty());
+
+        std::fs::remove_dir_all(&temp_dir).unwrap();
+    }
+
+    #[test]
+    fn removed_step_takes_every_step_waiting_on_it() {
+        let mut blocked = vec![
+            step_task("normalize", &["remux"], Some("remux")),
+            step_task("hd", &["normalize"], Some("normalize")),
+            step_task("thumbs", &["hd", "poster"], Some("hd")),
+            step_task("poster", &["cover"], Some("cover")),
+        ];
+
+        let taken: Vec<String> = take_dependents(&mut blocked, "remux")
+            .into_iter()
+            .map(|task| task.id)
+            .collect();
+        assert_eq!(taken, vec!["normalize", "hd", "thumbs"]);
+        assert_eq!(blocked.len(), 1);
+        assert_eq!(blocked[0].id, "poster");
+
+        assert!(take_dependents(&mut blocked, "remux").is_empty());
+        assert_eq!(take_dependents(&mut blocked, "cover").len(), 1);
+        assert!(blocked.is_empty());
+    }
+
+    #[test]
+    fn skipped_intermediate_keeps_the_existing_file() {
+        let temp_dir =
+            std::env::temp_dir().join(format!("frame-pipeline-skip-{}", std::process::id()));
+        std::fs::create_dir_all(&temp_dir).unwrap();
+        let existing = temp_dir.join("source.mkv");
+        std::fs::write(&existing, b"user file").unwrap();
+        let existing_path = existing.to_string_lossy().to_string();
+
+        let mut remux = step_task("remux", &[], None);
+        remux.job.as_mut().unwrap().intermediate = true;
+        assert!(
+            step_output(&remux, &existing_path, true)
+                .unwrap()
+                .intermediate
+        );
+
+        // The step skipped because its output already existed.
+        let skipped = step_output(&remux, &existing_path, false).unwrap();
+        assert!(!skipped.intermediate);
+
+        let mut outputs = HashMap::from([("remux".to_string(), skipped)]);
+        collect_job_outputs("job", &mut outputs, &[], &temp_dir);
+        assert!(existing.exists());
+        assert!(outputs.is_empty());
+
+        std::fs::remove_dir_all(&temp_dir).unwrap();
+    }
+}
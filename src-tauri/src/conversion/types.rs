@@ -59,6 +59,9 @@ pub struct ConversionConfig {
     pub video_codec: String,
     pub video_bitrate_mode: String,
     pub video_bitrate: String,
+    /// Desired output size in megabytes (10^6 bytes) for `video_bitrate_mode == "target_size"`.
+    #[serde(default)]
+    pub target_size_mb: Option<f64>,
     pub audio_codec: String,
     pub audio_bitrate: String,
     pub audio_channels: String,
@@ -80,6 +83,9 @@ pub struct ConversionConfig {
     pub preset: String,
     pub start_time: Option<String>,
     pub end_time: Option<String>,
+    /// Ranges to keep and join in order. Replaces `start_time`/`end_time` when non-empty.
+    #[serde(default)]
+    pub segments: Vec<TimeSegment>,
     #[serde(default)]
     pub metadata: MetadataConfig,
     #[serde(default = "default_rotation")]
@@ -106,6 +112,62 @@ pub struct ConversionConfig {
     pub gif_dither: String,
     #[serde(default = "default_gif_loop")]
     pub gif_loop: u16,
+    #[serde(default)]
+    pub two_pass: bool,
+    /// Retry once with the matching software encoder if an NVENC encoder fails to initialize.
+    #[serde(default)]
+    pub hw_fallback: bool,
+    /// Directory outputs are written to instead of the source's directory.
+    #[serde(default)]
+    pub output_directory: Option<String>,
+    /// File name template, e.g. `{name}_{height}p`. Used when the task has no explicit name.
+    #[serde(default)]
+    pub output_template: Option<String>,
+    /// Encoder and filter threads per ffmpeg process; `None` lets ffmpeg decide.
+    #[serde(default)]
+    pub threads: Option<u32>,
+    #[serde(default)]
+    pub process_priority: ProcessPriority,
+    /// Further renditions encoded from the same decode as the main output.
+    #[serde(default)]
+    pub additional_outputs: Vec<OutputSpec>,
+}
+
+/// A rendition written next to the main output. Unset fields keep the main
+/// output's settings.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct OutputSpec {
+    /// File name without extension; defaults to the main output's name plus the resolution.
+    #[serde(default)]
+    pub output_name: Option<String>,
+    pub container: String,
+    pub video_codec: String,
+    pub resolution: String,
+    #[serde(default)]
+    pub video_bitrate_mode: Option<String>,
+    #[serde(default)]
+    pub video_bitrate: Option<String>,
+    #[serde(default)]
+    pub crf: Option<u8>,
+    #[serde(default)]
+    pub preset: Option<String>,
+    #[serde(default)]
+    pub audio_codec: Option<String>,
+    #[serde(default)]
+    pub audio_bitrate: Option<String>,
+}
+
+/// OS scheduling priority for a task's sidecars, so a batch can run in the
+/// background without making the machine unusable.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ProcessPriority {
+    Low,
+    /// Leaves the priority inherited from the app unchanged.
+    #[default]
+    Normal,
+    High,
 }
 
 fn default_rotation() -> String {
@@ -136,6 +198,13 @@ fn default_gif_loop() -> u16 {
     0
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TimeSegment {
+    pub start: String,
+    pub end: String,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 #[serde(rename_all = "camelCase")]
 pub struct CropConfig {
@@ -173,10 +242,81 @@ pub enum MetadataMode {
     Replace,
 }
 
-#[derive(Clone, Serialize)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum TaskStatus {
+    Queued,
+    Running,
+    Paused,
+    Completed,
+    Failed,
+    Cancelled,
+    Interrupted,
+    Skipped,
+    /// Failed and waiting out the retry backoff before it is queued again.
+    Retrying,
+    /// Ended early on request; the output written so far was kept.
+    Stopped,
+    /// A pipeline step waiting for the steps it depends on.
+    Blocked,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct TaskState {
+    pub id: String,
+    pub file_path: String,
+    pub output_name: Option<String>,
+    /// Pipeline job this task is a step of.
+    pub job_id: Option<String>,
+    pub status: TaskStatus,
+    pub priority: i32,
+    /// Zero-based position in the pending queue; only set for queued tasks.
+    pub position: Option<usize>,
+    pub pid: Option<u32>,
+    /// Unix timestamps in milliseconds.
+    pub started_at: Option<u64>,
+    pub finished_at: Option<u64>,
+    pub progress: f64,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueState {
+    pub max_concurrency: usize,
+    pub scheduler: SchedulerConfig,
+    /// Summed cost of the running tasks, compared against the adaptive budgets.
+    pub in_use: TaskCost,
+    pub schedule: QueueSchedule,
+    /// False while the schedule holds queued tasks outside its window.
+    pub window_open: bool,
+    /// Set by `pause_all`; queued tasks wait until `resume_all`.
+    pub paused: bool,
+    pub tasks: Vec<TaskState>,
+}
+
+#[derive(Clone, Serialize, Default)]
 pub struct ProgressPayload {
     pub id: String,
     pub progress: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fps: Option<f64>,
+    /// Encode speed as a multiple of realtime.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub speed: Option<f64>,
+    /// Output bitrate in kbit/s.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bitrate: Option<f64>,
+    /// Bytes written to the output so far.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_size: Option<u64>,
+    /// Encoded media position in seconds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub out_time: Option<f64>,
+    /// Estimated seconds until the task finishes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub eta: Option<f64>,
 }
 
 #[derive(Clone, Serialize)]
@@ -188,12 +328,103 @@ pub struct StartedPayload {
 pub struct CompletedPayload {
     pub id: String,
     pub output_path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target_size: Option<u64>,
+    /// Set when the collision policy skipped the task because the output already existed.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub skipped: bool,
+    /// Result of probing the finished output; absent when it was not checked.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verification: Option<OutputVerification>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encoder_fallback: Option<EncoderFallback>,
+    /// Set when the task was stopped early and the output only covers part of the input.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub partial: bool,
+    /// Seconds of media in a partial output, as probed after ffmpeg finalized it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encoded_duration: Option<f64>,
+    /// Renditions written alongside `output_path`, in `additional_outputs` order.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub additional_outputs: Vec<CompletedOutput>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CompletedOutput {
+    pub output_path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verification: Option<OutputVerification>,
+}
+
+/// Recorded when a hardware encode was retried with a software encoder.
+#[derive(Debug, Clone, Serialize)]
+pub struct EncoderFallback {
+    pub from: String,
+    pub to: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum VerificationStatus {
+    Passed,
+    Warning,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OutputVerification {
+    pub status: VerificationStatus,
+    pub issues: Vec<String>,
 }
 
 #[derive(Clone, Serialize)]
 pub struct ErrorPayload {
     pub id: String,
     pub error: String,
+    pub kind: String,
+    /// The ffmpeg stderr line the error was classified from.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub excerpt: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct SchedulePayload {
+    pub enabled: bool,
+    pub open: bool,
+    pub start: String,
+    pub end: String,
+    /// Minutes until the window next opens or closes; `None` while disabled.
+    pub next_change_minutes: Option<u32>,
+    /// Queued tasks waiting for the window.
+    pub waiting: usize,
+    /// Running tasks suspended because the window closed.
+    pub held: usize,
+    pub reason: Option<String>,
+}
+
+#[derive(Clone, Serialize)]
+pub struct PipelinePayload {
+    pub job_id: String,
+    pub step_id: String,
+    pub status: TaskStatus,
+    pub completed_steps: usize,
+    pub total_steps: usize,
+}
+
+#[derive(Clone, Serialize)]
+pub struct RetryPayload {
+    pub id: String,
+    /// The attempt about to be made, starting at 2 for the first retry.
+    pub attempt: u32,
+    pub max_attempts: u32,
+    pub delay_ms: u64,
+    pub error: String,
+    pub kind: String,
 }
 
 #[derive(Clone, Serialize)]
@@ -257,10 +488,191 @@ pub struct FfprobeTags {
     pub description_upper: Option<String>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
 pub struct ConversionTask {
     pub id: String,
     pub file_path: String,
     pub output_name: Option<String>,
     pub config: ConversionConfig,
+    /// Higher values are started first; tasks with equal priority keep queue order.
+    #[serde(default)]
+    pub priority: i32,
+    #[serde(default)]
+    pub collision_policy: CollisionPolicy,
+    /// Overrides the manager's global retry policy for this task.
+    #[serde(default)]
+    pub retry_policy: Option<RetryPolicy>,
+    /// Failed runs so far, kept across restarts so retries stay bounded.
+    #[serde(default)]
+    pub attempts: u32,
+    /// Set when the task is one step of a multi-step job.
+    #[serde(default)]
+    pub job: Option<JobLink>,
+}
+
+/// Where a task sits in a multi-step job.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct JobLink {
+    pub job_id: String,
+    /// Steps that must complete before this one starts.
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+    /// Step whose output replaces `file_path` as this step's input.
+    #[serde(default)]
+    pub input_from: Option<String>,
+    /// The output only feeds later steps and is deleted once they finish.
+    #[serde(default)]
+    pub intermediate: bool,
+    /// The job's input. Outputs are named after it rather than after the
+    /// intermediate a step reads, so they land beside the source.
+    #[serde(default)]
+    pub source_path: String,
+}
+
+/// One step of a job submitted with `queue_pipeline`. Steps may only depend
+/// on steps listed before them.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PipelineStep {
+    pub id: String,
+    pub config: ConversionConfig,
+    #[serde(default)]
+    pub output_name: Option<String>,
+    #[serde(default)]
+    pub depends_on: Vec<String>,
+    /// Earlier step whose output this step reads; `None` reads the job's source.
+    #[serde(default)]
+    pub input_from: Option<String>,
+    #[serde(default)]
+    pub intermediate: bool,
+}
+
+/// Output of a finished pipeline step, kept until the job no longer needs it.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct StepOutput {
+    pub job_id: String,
+    pub path: String,
+    pub intermediate: bool,
+}
+
+/// How often a failed task is put back in the queue. `max_attempts` counts
+/// the first run, so the default of 1 never retries.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    /// Delay before the first retry.
+    pub backoff_ms: u64,
+    /// Factor applied to the delay for every further retry.
+    pub backoff_multiplier: f64,
+    /// Error kinds, as reported in `conversion-error`, that are worth retrying.
+    pub retryable_kinds: Vec<String>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 1,
+            backoff_ms: 5_000,
+            backoff_multiplier: 2.0,
+            retryable_kinds: [
+                "io",
+                "probe",
+                "worker",
+                "invalid_data",
+                "nvenc_session_limit",
+            ]
+            .into_iter()
+            .map(String::from)
+            .collect(),
+        }
+    }
+}
+
+/// How the manager decides whether the next queued task may start.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum SchedulerMode {
+    /// Up to `max_concurrency` tasks run at once, whatever they are.
+    #[default]
+    Fixed,
+    /// Tasks start while their cost fits the CPU and GPU budgets.
+    Adaptive,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SchedulerConfig {
+    pub mode: SchedulerMode,
+    /// CPU units available to adaptive scheduling; a software 1080p H.264
+    /// encode costs one unit.
+    pub cpu_budget: f64,
+    /// GPU units available to adaptive scheduling; a hardware encoder session
+    /// costs one unit.
+    pub gpu_budget: f64,
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        // A software encode keeps roughly four cores busy.
+        let cores = std::thread::available_parallelism().map_or(4, |n| n.get());
+        Self {
+            mode: SchedulerMode::Fixed,
+            cpu_budget: (cores / 4).max(1) as f64,
+            gpu_budget: 2.0,
+        }
+    }
+}
+
+/// Daily local-time window in which queued tasks may start, e.g. 22:00–06:00.
+/// Outside it running tasks are suspended until the window opens again.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct QueueSchedule {
+    pub enabled: bool,
+    /// `HH:MM`; a start later than `end` spans midnight.
+    pub start: String,
+    pub end: String,
+}
+
+impl Default for QueueSchedule {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            start: "22:00".to_string(),
+            end: "06:00".to_string(),
+        }
+    }
+}
+
+/// Resources a task is expected to use while it runs, in the units of
+/// `SchedulerConfig`'s budgets.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Default)]
+pub struct TaskCost {
+    pub cpu: f64,
+    pub gpu: f64,
+}
+
+/// What to do when the output path already exists or is claimed by another
+/// running task. Resolved by the manager right before the task is started.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum CollisionPolicy {
+    #[default]
+    Overwrite,
+    Skip,
+    /// Appends ` (1)`, ` (2)`, ... to the file stem until the name is free.
+    AutoIncrement,
+    Fail,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum QueuePosition {
+    Top,
+    Bottom,
+    Index(usize),
 }
@@ -6,18 +6,27 @@ use tauri_plugin_shell::ShellExt;
 use tauri_plugin_shell::process::CommandEvent;
 use tokio::sync::mpsc;
 
-use crate::conversion::args::{add_metadata_flags, build_output_path};
+use crate::conversion::args::{
+    add_encoder_thread_args, add_filter_thread_args, add_metadata_flags,
+};
 use crate::conversion::codec::{
     add_audio_codec_args, add_fps_args, add_subtitle_codec_args, add_video_codec_args,
 };
+use crate::conversion::diagnostics::{StderrTail, classify_ffmpeg_failure};
 use crate::conversion::error::ConversionError;
 use crate::conversion::filters::{build_audio_filters, build_video_filters};
-use crate::conversion::manager::ManagerMessage;
+use crate::conversion::manager::{ManagerMessage, TaskProcesses};
 use crate::conversion::types::{
-    CompletedPayload, ConversionConfig, ConversionTask, LogPayload, MetadataMode, ProgressPayload,
-    StartedPayload,
+    ConversionConfig, ConversionTask, LogPayload, MetadataMode, ProgressPayload, StartedPayload,
+};
+use crate::conversion::utils::{
+    FRAME_REGEX, finish_partial_output, parse_time, partial_output_path,
+    sanitize_external_tool_path, task_temp_dir,
+};
+use crate::conversion::verify::check_verification;
+use crate::conversion::worker::{
+    completed_payload, emit_progress, spawn_sidecar, verify_finished_output,
 };
-use crate::conversion::utils::{FRAME_REGEX, parse_time, sanitize_external_tool_path};
 
 pub(crate) fn build_upscale_encode_args(
     output_frames_dir: &Path,
@@ -27,7 +36,9 @@ pub(crate) fn build_upscale_encode_args(
     config: &ConversionConfig,
     pixel_format: Option<String>,
 ) -> Vec<String> {
-    let mut enc_args = vec![
+    let mut enc_args = Vec::new();
+    add_filter_thread_args(&mut enc_args, config);
+    enc_args.extend([
         "-framerate".to_string(),
         source_fps.to_string(),
         "-start_number".to_string(),
@@ -37,7 +48,7 @@ pub(crate) fn build_upscale_encode_args(
             .join("frame_%08d.png")
             .to_string_lossy()
             .to_string(),
-    ];
+    ]);
 
     if let Some(start) = &config.start_time {
         if !start.is_empty() {
@@ -125,6 +136,7 @@ pub(crate) fn build_upscale_encode_args(
         enc_args.push("yuv420p".to_string());
     }
 
+    add_encoder_thread_args(&mut enc_args, config);
     enc_args.push("-shortest".to_string());
     enc_args.push("-y".to_string());
     enc_args.push(output_path.to_string());
@@ -243,6 +255,8 @@ pub async fn run_upscale_worker(
     app: AppHandle,
     tx: mpsc::Sender<ManagerMessage>,
     task: ConversionTask,
+    output_path: String,
+    processes: TaskProcesses,
 ) -> Result<(), ConversionError> {
     let mode = task
         .config
@@ -252,12 +266,6 @@ pub async fn run_upscale_worker(
 
     let (scale, model_name) = resolve_upscale_mode(mode)?;
 
-    let output_path = build_output_path(
-        &task.file_path,
-        &task.config.container,
-        task.output_name.clone(),
-    );
-
     let probe = crate::conversion::probe::probe_media_file(&app, &task.file_path)
         .await
         .map_err(|e| ConversionError::Worker(format!("Probe failed: {}", e)))?;
@@ -284,7 +292,7 @@ pub async fn run_upscale_worker(
     let active_duration = (end_t - start_t).max(0.0);
     let total_frames = (active_duration * fps).ceil() as u32;
 
-    let temp_dir = std::env::temp_dir().join(format!("frame_upscale_{}", task.id));
+    let temp_dir = task_temp_dir("upscale", &task.id);
     if temp_dir.exists() {
         let _ = std::fs::remove_dir_all(&temp_dir);
     }
@@ -304,15 +312,18 @@ pub async fn run_upscale_worker(
         },
     );
 
-    let _ = app_clone.emit(
-        "conversion-progress",
+    emit_progress(
+        &app_clone,
+        &tx,
         ProgressPayload {
             id: id_clone.clone(),
             progress: 0.0,
+            ..Default::default()
         },
     );
 
     let mut dec_args = Vec::new();
+    add_filter_thread_args(&mut dec_args, &task.config);
 
     // Hardware decode acceleration (only -hwaccel, no output_format since we need CPU frames)
     if task.config.hw_decode {
@@ -368,6 +379,7 @@ pub async fn run_upscale_worker(
     dec_args.push(fps.to_string());
     dec_args.push("-vsync".to_string());
     dec_args.push("cfr".to_string());
+    add_encoder_thread_args(&mut dec_args, &task.config);
 
     dec_args.push(
         input_frames_dir
@@ -376,20 +388,7 @@ pub async fn run_upscale_worker(
             .to_string(),
     );
 
-    let (mut dec_rx, dec_child) = app
-        .shell()
-        .sidecar("ffmpeg")
-        .map_err(|e| ConversionError::Shell(e.to_string()))?
-        .args(dec_args)
-        .spawn()
-        .map_err(|e| ConversionError::Shell(e.to_string()))?;
-
-    let _ = tx
-        .send(ManagerMessage::TaskStarted(
-            task.id.clone(),
-            dec_child.pid(),
-        ))
-        .await;
+    let (mut dec_rx, dec_child) = spawn_sidecar(&app, &processes, "ffmpeg", dec_args)?;
 
     let mut decode_success = false;
 
@@ -411,11 +410,13 @@ pub async fn run_upscale_worker(
                             if let Ok(current_frame) = frame_match.as_str().parse::<u32>() {
                                 let decode_progress =
                                     (current_frame as f64 / total_frames as f64) * 5.0;
-                                let _ = app_clone.emit(
-                                    "conversion-progress",
+                                emit_progress(
+                                    &app_clone,
+                                    &tx,
                                     ProgressPayload {
                                         id: id_clone.clone(),
                                         progress: decode_progress.min(5.0),
+                                        ..Default::default()
                                     },
                                 );
                             }
@@ -430,6 +431,7 @@ pub async fn run_upscale_worker(
             _ => {}
         }
     }
+    processes.unregister(dec_child.pid());
 
     if !decode_success {
         let _ = std::fs::remove_dir_all(&temp_dir);
@@ -486,20 +488,8 @@ pub async fn run_upscale_worker(
         "0".to_string(),
     ];
 
-    let (mut upscale_rx, upscale_child) = app
-        .shell()
-        .sidecar("realesrgan-ncnn-vulkan")
-        .map_err(|e| ConversionError::Shell(e.to_string()))?
-        .args(upscaler_args)
-        .spawn()
-        .map_err(|e| ConversionError::Shell(e.to_string()))?;
-
-    let _ = tx
-        .send(ManagerMessage::TaskStarted(
-            task.id.clone(),
-            upscale_child.pid(),
-        ))
-        .await;
+    let (mut upscale_rx, upscale_child) =
+        spawn_sidecar(&app, &processes, "realesrgan-ncnn-vulkan", upscaler_args)?;
 
     let mut upscale_success = false;
     let mut last_error = String::new();
@@ -539,11 +529,13 @@ pub async fn run_upscale_worker(
 
                 if progress > last_upscale_progress {
                     last_upscale_progress = progress;
-                    let _ = app_clone.emit(
-                        "conversion-progress",
+                    emit_progress(
+                        &app_clone,
+                        &tx,
                         ProgressPayload {
                             id: id_clone.clone(),
                             progress: progress.min(90.0),
+                            ..Default::default()
                         },
                     );
                 }
@@ -554,6 +546,7 @@ pub async fn run_upscale_worker(
             break;
         }
     }
+    processes.unregister(upscale_child.pid());
     if !upscale_success {
         let _ = std::fs::remove_dir_all(&temp_dir);
         return Err(ConversionError::Worker(format!(
@@ -562,34 +555,24 @@ pub async fn run_upscale_worker(
         )));
     }
 
+    let partial_path = partial_output_path(&output_path, &task.id);
     let enc_args = build_upscale_encode_args(
         &output_frames_dir,
         &task.file_path,
-        &output_path,
+        &partial_path.to_string_lossy(),
         fps,
         &task.config,
-        probe.pixel_format,
+        probe.pixel_format.clone(),
     );
 
-    let (mut enc_rx, enc_child) = app
-        .shell()
-        .sidecar("ffmpeg")
-        .map_err(|e| ConversionError::Shell(e.to_string()))?
-        .args(enc_args)
-        .spawn()
-        .map_err(|e| ConversionError::Shell(e.to_string()))?;
-
-    let _ = tx
-        .send(ManagerMessage::TaskStarted(
-            task.id.clone(),
-            enc_child.pid(),
-        ))
-        .await;
+    let (mut enc_rx, enc_child) = spawn_sidecar(&app, &processes, "ffmpeg", enc_args)?;
 
+    let mut stderr_tail = StderrTail::default();
     while let Some(event) = enc_rx.recv().await {
         match event {
             CommandEvent::Stderr(ref line_bytes) => {
                 let line = String::from_utf8_lossy(line_bytes);
+                stderr_tail.push(&line);
                 let _ = app_clone.emit(
                     "conversion-log",
                     LogPayload {
@@ -604,11 +587,13 @@ pub async fn run_upscale_worker(
                             if let Ok(current_frame) = frame_match.as_str().parse::<u32>() {
                                 let encode_progress =
                                     90.0 + (current_frame as f64 / total_frames as f64) * 10.0;
-                                let _ = app_clone.emit(
-                                    "conversion-progress",
+                                emit_progress(
+                                    &app_clone,
+                                    &tx,
                                     ProgressPayload {
                                         id: id_clone.clone(),
                                         progress: encode_progress.min(99.0),
+                                        ..Default::default()
                                     },
                                 );
                             }
@@ -617,21 +602,34 @@ pub async fn run_upscale_worker(
                 }
             }
             CommandEvent::Terminated(payload) => {
+                processes.unregister(enc_child.pid());
                 let _ = std::fs::remove_dir_all(&temp_dir);
-                if payload.code == Some(0) {
+                let success = payload.code == Some(0);
+                let finished = finish_partial_output(&partial_path, &output_path, success);
+                if success {
+                    finished?;
+                    let verification = verify_finished_output(
+                        &app,
+                        &task.id,
+                        &output_path,
+                        &task.config,
+                        Some(&probe),
+                        active_duration,
+                    )
+                    .await;
+                    check_verification(&output_path, &verification)?;
                     let _ = app.emit(
                         "conversion-completed",
-                        CompletedPayload {
-                            id: task.id.clone(),
+                        completed_payload(
+                            task.id.clone(),
                             output_path,
-                        },
+                            &task.config,
+                            Some(verification),
+                        ),
                     );
                     return Ok(());
                 } else {
-                    return Err(ConversionError::Worker(format!(
-                        "Encoder failed with code {:?}",
-                        payload.code
-                    )));
+                    return Err(classify_ffmpeg_failure(&stderr_tail, payload.code));
                 }
             }
             _ => {}
@@ -639,6 +637,7 @@ pub async fn run_upscale_worker(
     }
 
     let _ = std::fs::remove_dir_all(&temp_dir);
+    let _ = finish_partial_output(&partial_path, &output_path, false);
     Err(ConversionError::Worker(
         "Encoder terminated unexpectedly before reporting exit status".to_string(),
     ))
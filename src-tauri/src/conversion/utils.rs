@@ -1,15 +1,30 @@
 use crate::conversion::media_rules;
 use once_cell::sync::Lazy;
 use regex::Regex;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 pub static FRAME_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"frame=\s*(\d+)").unwrap());
 
 pub static DURATION_REGEX: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"Duration:\s*(\d+(?::\d+){0,3}(?:\.\d+)?)").unwrap());
 
-pub static TIME_REGEX: Lazy<Regex> =
-    Lazy::new(|| Regex::new(r"time=\s*(\d+(?::\d+){0,3}(?:\.\d+)?)").unwrap());
+pub static TEMPLATE_TOKEN_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"\{(\w*)\}").unwrap());
+
+#[cfg(windows)]
+pub const NULL_OUTPUT: &str = "NUL";
+#[cfg(not(windows))]
+pub const NULL_OUTPUT: &str = "/dev/null";
+
+/// Latest keyframe at or before `time`. Snapping backwards keeps the requested
+/// start inside the cut, at the cost of up to one GOP of extra lead-in.
+pub fn snap_to_keyframe(time: f64, keyframes: &[f64]) -> Option<f64> {
+    const TOLERANCE: f64 = 0.001;
+    keyframes
+        .iter()
+        .copied()
+        .filter(|k| *k <= time + TOLERANCE)
+        .max_by(|a, b| a.total_cmp(b))
+}
 
 pub fn parse_frame_rate_string(value: Option<&str>) -> Option<f64> {
     let value = value?.trim();
@@ -53,6 +68,38 @@ pub fn is_videotoolbox_codec(codec: &str) -> bool {
     matches!(codec, "h264_videotoolbox" | "hevc_videotoolbox")
 }
 
+pub fn is_stream_copy(codec: &str) -> bool {
+    codec == "copy"
+}
+
+pub fn is_bitrate_targeted(video_bitrate_mode: &str) -> bool {
+    matches!(video_bitrate_mode, "bitrate" | "target_size")
+}
+
+pub fn is_lossless_audio_codec(codec: &str) -> bool {
+    matches!(codec, "flac" | "alac" | "pcm_s16le")
+}
+
+/// Video bitrate in kbps that fits `duration` seconds into `target_mb` megabytes
+/// once the audio streams and a small muxing overhead are accounted for.
+pub fn target_video_bitrate_kbps(target_mb: f64, duration: f64, audio_kbps: f64) -> Option<f64> {
+    const MUXING_OVERHEAD: f64 = 0.02;
+
+    if target_mb <= 0.0 || duration <= 0.0 {
+        return None;
+    }
+
+    let total_kbps = target_mb * 8000.0 / duration * (1.0 - MUXING_OVERHEAD);
+    let video_kbps = (total_kbps - audio_kbps).floor();
+    (video_kbps >= 1.0).then_some(video_kbps)
+}
+
+/// SVT-AV1 is left out: its multi-pass mode is not driven reliably through
+/// `-pass`/`-passlogfile`, so its bitrate-targeted encodes run in one pass.
+pub fn supports_two_pass(codec: &str) -> bool {
+    matches!(codec, "libx264" | "libx265" | "vp9" | "libvpx-vp9")
+}
+
 pub fn map_nvenc_preset(preset: &str) -> String {
     match preset {
         "fast" | "medium" | "slow" => preset.to_string(),
@@ -64,6 +111,62 @@ pub fn map_nvenc_preset(preset: &str) -> String {
     }
 }
 
+/// Software encoder producing the same format as an NVENC encoder.
+pub fn software_encoder_for(nvenc_codec: &str) -> Option<&'static str> {
+    match nvenc_codec {
+        "h264_nvenc" => Some("libx264"),
+        "hevc_nvenc" => Some("libx265"),
+        "av1_nvenc" => Some("libsvtav1"),
+        _ => None,
+    }
+}
+
+/// Software preset with a similar speed/quality trade-off to an NVENC preset.
+/// SVT-AV1 takes numeric presets, where lower is slower.
+pub fn map_nvenc_preset_to_software(preset: &str, software_codec: &str) -> String {
+    let x26x = match preset {
+        "p1" => "veryfast",
+        "p2" => "faster",
+        "p3" => "fast",
+        "p5" => "slow",
+        "p6" => "slower",
+        "p7" => "veryslow",
+        "ultrafast" | "superfast" | "veryfast" | "faster" | "fast" | "slow" | "slower"
+        | "veryslow" => preset,
+        _ => "medium",
+    };
+    if software_codec != "libsvtav1" {
+        return x26x.to_string();
+    }
+
+    match x26x {
+        "ultrafast" | "superfast" | "veryfast" => "12",
+        "faster" => "10",
+        "fast" => "9",
+        "slow" => "6",
+        "slower" => "5",
+        "veryslow" => "4",
+        _ => "8",
+    }
+    .to_string()
+}
+
+/// NVENC constant-quality (`-cq`) value for the 0-100 quality slider.
+pub fn nvenc_cq(quality: u32) -> u8 {
+    (52.0 - (quality as f64 / 2.0)).round().clamp(1.0, 51.0) as u8
+}
+
+/// CRF matching the constant-quality value NVENC is given for `quality`.
+/// NVENC's `-cq` and x264/x265 CRF share the 0-51 scale; SVT-AV1 uses 0-63.
+pub fn nvenc_quality_to_crf(quality: u32, software_codec: &str) -> u8 {
+    let cq = nvenc_cq(quality);
+    if software_codec == "libsvtav1" {
+        (cq as f64 * 63.0 / 51.0).round() as u8
+    } else {
+        cq
+    }
+}
+
 pub fn parse_time(time_str: &str) -> Option<f64> {
     let parts: Vec<&str> = time_str.split(':').collect();
     match parts.len() {
@@ -98,6 +201,124 @@ pub fn get_hwaccel_args(video_codec: &str) -> Vec<String> {
     }
 }
 
+/// Scratch directory for a task's intermediate files (upscale frames, passlogs).
+/// `cancel_task` removes these by the same naming scheme.
+pub fn task_temp_dir(kind: &str, id: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("frame_{}_{}", kind, id))
+}
+
+/// Hidden sibling of `output_path` that ffmpeg writes into until the encode
+/// succeeds. The extension is kept so ffmpeg still picks the right muxer.
+pub fn partial_output_path(output_path: &str, id: &str) -> PathBuf {
+    let path = Path::new(output_path);
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let name = match path.extension() {
+        Some(ext) => format!(".{}.{}.part.{}", stem, id, ext.to_string_lossy()),
+        None => format!(".{}.{}.part", stem, id),
+    };
+    path.with_file_name(name)
+}
+
+/// Moves a finished partial output onto its destination, or deletes it when
+/// the encode did not succeed. The destination is only touched on success.
+pub fn finish_partial_output(
+    partial_path: &Path,
+    output_path: &str,
+    success: bool,
+) -> std::io::Result<()> {
+    if success {
+        std::fs::rename(partial_path, output_path)
+    } else {
+        match std::fs::remove_file(partial_path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Today's local date as `YYYY-MM-DD`, for the `{date}` output template token.
+pub fn current_date() -> String {
+    let (year, month, day) = match local_clock() {
+        Some(clock) => clock.date,
+        None => civil_from_days(utc_seconds() as i64 / 86_400),
+    };
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Minutes since local midnight, falling back to UTC if the local time
+/// cannot be read.
+pub fn local_minutes_of_day() -> u32 {
+    match local_clock() {
+        Some(clock) => clock.minutes,
+        None => ((utc_seconds() / 60) % 1440) as u32,
+    }
+}
+
+struct LocalClock {
+    date: (i64, u32, u32),
+    minutes: u32,
+}
+
+fn local_clock() -> Option<LocalClock> {
+    #[cfg(windows)]
+    {
+        let now = unsafe { windows::Win32::System::SystemInformation::GetLocalTime() };
+        Some(LocalClock {
+            date: (
+                i64::from(now.wYear),
+                u32::from(now.wMonth),
+                u32::from(now.wDay),
+            ),
+            minutes: u32::from(now.wHour) * 60 + u32::from(now.wMinute),
+        })
+    }
+
+    #[cfg(not(windows))]
+    {
+        #[cfg(unix)]
+        unsafe {
+            let now = libc::time(std::ptr::null_mut());
+            let mut tm: libc::tm = std::mem::zeroed();
+            if !libc::localtime_r(&now, &mut tm).is_null() {
+                return Some(LocalClock {
+                    date: (
+                        i64::from(tm.tm_year) + 1900,
+                        (tm.tm_mon + 1) as u32,
+                        tm.tm_mday as u32,
+                    ),
+                    minutes: (tm.tm_hour * 60 + tm.tm_min) as u32,
+                });
+            }
+        }
+
+        None
+    }
+}
+
+fn utc_seconds() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Converts days since 1970-01-01 to a proleptic Gregorian date.
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = (if mp < 10 { mp + 3 } else { mp - 9 }) as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
 pub fn sanitize_external_tool_path(path: &Path) -> String {
     #[cfg(windows)]
     {
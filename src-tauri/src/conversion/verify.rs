@@ -0,0 +1,118 @@
+use crate::conversion::error::ConversionError;
+use crate::conversion::media_rules::container_supports_audio;
+use crate::conversion::types::{
+    ConversionConfig, OutputVerification, ProbeMetadata, VerificationStatus,
+};
+use crate::conversion::utils::{is_audio_only_container, parse_time};
+
+/// Absolute slack allowed between the expected and the probed output duration.
+/// Container start offsets and the last GOP routinely account for a fraction of this.
+const DURATION_TOLERANCE_SECS: f64 = 1.0;
+/// Relative slack for long outputs, where timestamp rounding adds up.
+const DURATION_TOLERANCE_RATIO: f64 = 0.02;
+
+/// Turns a failed verification into the error the task ends with, so the app
+/// and `frame-cli` both report such an output as failed.
+pub fn check_verification(
+    output_path: &str,
+    verification: &OutputVerification,
+) -> Result<(), ConversionError> {
+    if verification.status != VerificationStatus::Failed {
+        return Ok(());
+    }
+    Err(ConversionError::Worker(format!(
+        "Output verification failed for {}: {}",
+        output_path,
+        verification.issues.join("; ")
+    )))
+}
+
+/// Checks a finished output against what the config asked for. Missing
+/// streams and outputs that end early fail verification; an unexpected track
+/// count or a longer output only warn, since keyframe-snapped copies and
+/// encoder padding legitimately run a little long.
+pub fn verify_output(
+    config: &ConversionConfig,
+    source: Option<&ProbeMetadata>,
+    expected_duration: f64,
+    output: &ProbeMetadata,
+) -> OutputVerification {
+    let mut failures = Vec::new();
+    let mut warnings = Vec::new();
+
+    let source_has_video = source.is_none_or(|s| s.video_codec.is_some());
+    if !is_audio_only_container(&config.container)
+        && source_has_video
+        && output.video_codec.is_none()
+    {
+        failures.push("Output has no video stream".to_string());
+    }
+
+    if let Some(expected) = expected_audio_streams(config, source) {
+        let actual = output.audio_tracks.len();
+        if expected > 0 && actual == 0 {
+            failures.push("Output has no audio stream".to_string());
+        } else if actual != expected {
+            warnings.push(format!(
+                "Output has {} audio stream(s), expected {}",
+                actual, expected
+            ));
+        }
+    }
+
+    if expected_duration > 0.0 {
+        match output.duration.as_deref().and_then(parse_time) {
+            Some(actual) => {
+                let tolerance =
+                    DURATION_TOLERANCE_SECS.max(expected_duration * DURATION_TOLERANCE_RATIO);
+                let message = format!(
+                    "Output duration {:.2}s differs from expected {:.2}s",
+                    actual, expected_duration
+                );
+                if actual < expected_duration - tolerance {
+                    failures.push(message);
+                } else if actual > expected_duration + tolerance {
+                    warnings.push(message);
+                }
+            }
+            None => warnings.push("Output duration could not be determined".to_string()),
+        }
+    }
+
+    let status = if !failures.is_empty() {
+        VerificationStatus::Failed
+    } else if !warnings.is_empty() {
+        VerificationStatus::Warning
+    } else {
+        VerificationStatus::Passed
+    };
+    failures.extend(warnings);
+
+    OutputVerification {
+        status,
+        issues: failures,
+    }
+}
+
+/// Number of audio streams the arguments map, or `None` when that cannot be
+/// known without the source probe.
+fn expected_audio_streams(
+    config: &ConversionConfig,
+    source: Option<&ProbeMetadata>,
+) -> Option<usize> {
+    if !container_supports_audio(&config.container) {
+        return Some(0);
+    }
+
+    match (source, config.selected_audio_tracks.is_empty()) {
+        (Some(source), true) => Some(source.audio_tracks.len()),
+        (Some(source), false) => Some(
+            config
+                .selected_audio_tracks
+                .iter()
+                .filter(|index| source.audio_tracks.iter().any(|t| t.index == **index))
+                .count(),
+        ),
+        (None, _) => None,
+    }
+}
@@ -1,87 +1,588 @@
 use tauri::{AppHandle, Emitter};
 use tauri_plugin_shell::ShellExt;
-use tauri_plugin_shell::process::CommandEvent;
-use tokio::sync::mpsc;
+use tauri_plugin_shell::process::{CommandChild, CommandEvent};
+use tokio::sync::{mpsc, watch};
 
-use crate::conversion::args::{build_ffmpeg_args, build_output_path};
+use crate::conversion::args::{
+    build_concat_list, build_ffmpeg_args, build_multi_output_args, build_two_pass_args,
+    expected_output_duration, output_variant_config, prepare_config, segment_bounds,
+    software_fallback_config, uses_concat_demuxer, uses_keyframe_trim, uses_two_pass,
+};
+use crate::conversion::diagnostics::{
+    StderrTail, classify_ffmpeg_failure, is_encoder_init_failure,
+};
 use crate::conversion::error::ConversionError;
-use crate::conversion::manager::ManagerMessage;
+use crate::conversion::manager::{ManagerMessage, TaskProcesses, set_process_priority};
+use crate::conversion::probe::{probe_keyframes, probe_media_file};
+use crate::conversion::progress::{ProgressParser, estimate_eta};
 use crate::conversion::types::{
-    CompletedPayload, ConversionTask, LogPayload, ProgressPayload, StartedPayload,
+    CompletedOutput, CompletedPayload, ConversionConfig, ConversionTask, EncoderFallback,
+    LogPayload, OutputVerification, ProbeMetadata, ProgressPayload, StartedPayload,
+    VerificationStatus,
 };
 use crate::conversion::upscale::run_upscale_worker;
-use crate::conversion::utils::{DURATION_REGEX, TIME_REGEX, parse_time};
+use crate::conversion::utils::{
+    DURATION_REGEX, finish_partial_output, parse_time, partial_output_path, snap_to_keyframe,
+    task_temp_dir,
+};
+use crate::conversion::verify::{check_verification, verify_output};
+
+/// Emits a progress event to the UI and mirrors it into the manager so the
+/// last known progress is available to `get_queue_state`.
+pub(crate) fn emit_progress(
+    app: &AppHandle,
+    tx: &mpsc::Sender<ManagerMessage>,
+    payload: ProgressPayload,
+) {
+    let _ = tx.try_send(ManagerMessage::TaskProgress(
+        payload.id.clone(),
+        payload.progress,
+    ));
+    let _ = app.emit("conversion-progress", payload);
+}
+
+/// Where a single ffmpeg invocation sits within the task's overall progress.
+/// Two-pass encodes split the bar in half; `remaining_media` is the media time
+/// still to be processed by later passes, so the ETA covers the whole task.
+#[derive(Debug, Clone, Copy)]
+struct PassSpan {
+    offset: f64,
+    share: f64,
+    remaining_media: f64,
+}
+
+impl PassSpan {
+    const FULL: PassSpan = PassSpan {
+        offset: 0.0,
+        share: 100.0,
+        remaining_media: 0.0,
+    };
+}
+
+/// Builds the completion event, reporting the achieved size next to the
+/// requested one for target-size encodes.
+pub(crate) fn completed_payload(
+    id: String,
+    output_path: String,
+    config: &ConversionConfig,
+    verification: Option<OutputVerification>,
+) -> CompletedPayload {
+    let output_size = std::fs::metadata(&output_path).ok().map(|m| m.len());
+    let target_size = config
+        .target_size_mb
+        .filter(|_| config.video_bitrate_mode == "target_size")
+        .map(|mb| (mb * 1_000_000.0) as u64);
+
+    CompletedPayload {
+        id,
+        output_path,
+        output_size,
+        target_size,
+        skipped: false,
+        verification,
+        encoder_fallback: None,
+        partial: false,
+        encoded_duration: None,
+        additional_outputs: Vec::new(),
+    }
+}
+
+/// Probes the finished output and logs every issue found. An output that
+/// cannot be probed at all fails verification.
+pub(crate) async fn verify_finished_output(
+    app: &AppHandle,
+    id: &str,
+    output_path: &str,
+    config: &ConversionConfig,
+    source: Option<&ProbeMetadata>,
+    expected_duration: f64,
+) -> OutputVerification {
+    let verification = match probe_media_file(app, output_path).await {
+        Ok(output) => verify_output(config, source, expected_duration, &output),
+        Err(e) => OutputVerification {
+            status: VerificationStatus::Failed,
+            issues: vec![format!("Output could not be probed: {}", e)],
+        },
+    };
+
+    for issue in &verification.issues {
+        let _ = app.emit(
+            "conversion-log",
+            LogPayload {
+                id: id.to_string(),
+                line: format!("[VERIFY] {}", issue),
+            },
+        );
+    }
+    verification
+}
+
+/// Moves a stream-copy cut point onto the preceding keyframe. If keyframes
+/// cannot be probed the requested start is kept and ffmpeg seeks on its own.
+async fn snap_cut_to_keyframe(app: &AppHandle, id: &str, file_path: &str, start: &str) -> String {
+    let Some(requested) = parse_time(start) else {
+        return start.to_string();
+    };
+
+    let (snapped, line) = match probe_keyframes(app, file_path, requested).await {
+        Ok(keyframes) => match snap_to_keyframe(requested, &keyframes) {
+            Some(snapped) => (
+                format!("{:.6}", snapped),
+                format!(
+                    "Stream copy trim: start snapped from {:.3}s to keyframe at {:.3}s",
+                    requested, snapped
+                ),
+            ),
+            None => (
+                start.to_string(),
+                format!(
+                    "Stream copy trim: no keyframe found before {:.3}s, keeping requested start",
+                    requested
+                ),
+            ),
+        },
+        Err(e) => (
+            start.to_string(),
+            format!(
+                "Stream copy trim: keyframe probe failed ({}), keeping requested start",
+                e
+            ),
+        ),
+    };
+
+    let _ = app.emit(
+        "conversion-log",
+        LogPayload {
+            id: id.to_string(),
+            line,
+        },
+    );
+    snapped
+}
 
 pub async fn run_ffmpeg_worker(
     app: AppHandle,
     tx: mpsc::Sender<ManagerMessage>,
-    task: ConversionTask,
+    mut task: ConversionTask,
+    output_path: String,
+    additional_paths: Vec<Option<String>>,
+    stop: watch::Receiver<bool>,
+    processes: TaskProcesses,
 ) -> Result<(), ConversionError> {
+    let probe = probe_media_file(&app, &task.file_path).await.ok();
+
+    if uses_keyframe_trim(&task.config) {
+        let (id, file_path) = (task.id.clone(), task.file_path.clone());
+        if task.config.segments.is_empty() {
+            let start = task.config.start_time.clone().unwrap_or_default();
+            let snapped = snap_cut_to_keyframe(&app, &id, &file_path, &start).await;
+            task.config.start_time = Some(snapped);
+        }
+        for segment in task.config.segments.iter_mut() {
+            segment.start = snap_cut_to_keyframe(&app, &id, &file_path, &segment.start).await;
+        }
+    }
+
+    let expected_duration = expected_output_duration(&task.config, probe.as_ref());
+    task.config = prepare_config(&task.config, probe.as_ref())?;
+
+    if task.config.video_bitrate_mode == "target_size" {
+        let _ = app.emit(
+            "conversion-log",
+            LogPayload {
+                id: task.id.clone(),
+                line: format!(
+                    "Target size {} MB: using video bitrate {}k",
+                    task.config.target_size_mb.unwrap_or(0.0),
+                    task.config.video_bitrate
+                ),
+            },
+        );
+    }
+
     if let Some(upscale_mode) = &task.config.ml_upscale {
         if upscale_mode != "none" && !upscale_mode.is_empty() {
-            return run_upscale_worker(app, tx, task).await;
+            // The upscale pipeline cannot be stopped early; dropping the
+            // receiver makes `stop_task` report that.
+            drop(stop);
+            return run_upscale_worker(app, tx, task, output_path, processes).await;
         }
     }
 
-    let output_path = build_output_path(
-        &task.file_path,
-        &task.config.container,
-        task.output_name.clone(),
-    );
-    let args = build_ffmpeg_args(&task.file_path, &output_path, &task.config);
+    if !task.config.additional_outputs.is_empty() {
+        return run_multi_output(
+            app,
+            tx,
+            task,
+            output_path,
+            additional_paths,
+            probe,
+            expected_duration,
+            stop,
+            processes,
+        )
+        .await;
+    }
 
-    let sidecar_command = app
-        .shell()
-        .sidecar("ffmpeg")
-        .map_err(|e| ConversionError::Shell(e.to_string()))?
-        .args(args);
+    let id = task.id.clone();
+    // ffmpeg writes to a hidden sibling that only replaces `output_path` on success,
+    // so a failed or cancelled encode never leaves a truncated file behind.
+    let partial_path = partial_output_path(&output_path, &id);
+    let encode_path = partial_path.to_string_lossy().to_string();
 
-    let (mut rx, child) = sidecar_command
-        .spawn()
-        .map_err(|e| ConversionError::Shell(e.to_string()))?;
+    let concat_dir = task_temp_dir("concat", &id);
+    let input_path = if uses_concat_demuxer(&task.config) {
+        std::fs::create_dir_all(&concat_dir)?;
+        let list_path = concat_dir.join("segments.ffconcat");
+        std::fs::write(
+            &list_path,
+            build_concat_list(&task.file_path, &segment_bounds(&task.config)),
+        )?;
+        list_path.to_string_lossy().to_string()
+    } else {
+        task.file_path.clone()
+    };
 
-    let id = task.id.clone();
+    let mut result = run_encode(
+        &app,
+        &tx,
+        &processes,
+        &input_path,
+        &encode_path,
+        &task.config,
+        expected_duration,
+        &stop,
+    )
+    .await;
 
-    let _ = tx
-        .send(ManagerMessage::TaskStarted(id.clone(), child.pid()))
+    let mut encoder_fallback = None;
+    let fallback = match &result {
+        Err(err) if is_encoder_init_failure(err) => {
+            software_fallback_config(&task.config).map(|config| (config, err.to_string()))
+        }
+        _ => None,
+    };
+    if let Some((fallback, reason)) = fallback {
+        let record = EncoderFallback {
+            from: task.config.video_codec.clone(),
+            to: fallback.video_codec.clone(),
+            reason,
+        };
+        let _ = app.emit(
+            "conversion-log",
+            LogPayload {
+                id: id.clone(),
+                line: format!(
+                    "[FALLBACK] {} failed to initialize, retrying with {}: {}",
+                    record.from, record.to, record.reason
+                ),
+            },
+        );
+        task.config = fallback;
+        result = run_encode(
+            &app,
+            &tx,
+            &processes,
+            &input_path,
+            &encode_path,
+            &task.config,
+            expected_duration,
+            &stop,
+        )
         .await;
+        encoder_fallback = Some(record);
+    }
 
-    let _ = app.emit("conversion-started", StartedPayload { id: id.clone() });
+    if concat_dir.exists() {
+        let _ = std::fs::remove_dir_all(&concat_dir);
+    }
+    let finished = finish_partial_output(&partial_path, &output_path, result.is_ok());
+    result?;
+    finished?;
+
+    // A stopped encode ends wherever ffmpeg was, so only its streams are checked.
+    let stopped = *stop.borrow();
+    let verification = verify_finished_output(
+        &app,
+        &id,
+        &output_path,
+        &task.config,
+        probe.as_ref(),
+        if stopped { 0.0 } else { expected_duration },
+    )
+    .await;
+    check_verification(&output_path, &verification)?;
+    let mut payload = completed_payload(id.clone(), output_path, &task.config, Some(verification));
+    payload.encoder_fallback = encoder_fallback;
+    if stopped {
+        mark_stopped(&app, &mut payload).await;
+    }
+    let _ = app.emit("conversion-completed", payload);
+    Ok(())
+}
 
+/// Flags a completion as a stopped encode and records how much media it kept.
+async fn mark_stopped(app: &AppHandle, payload: &mut CompletedPayload) {
+    payload.partial = true;
+    payload.encoded_duration = probe_media_file(app, &payload.output_path)
+        .await
+        .ok()
+        .and_then(|output| output.duration.as_deref().and_then(parse_time));
     let _ = app.emit(
-        "conversion-progress",
-        ProgressPayload {
-            id: id.clone(),
-            progress: 0.0,
+        "conversion-log",
+        LogPayload {
+            id: payload.id.clone(),
+            line: format!(
+                "[STOP] Stopped early, kept {:.1}s of output",
+                payload.encoded_duration.unwrap_or(0.0)
+            ),
         },
     );
+}
+
+/// Encodes the main output and every additional rendition with a single
+/// ffmpeg run, so the source is only decoded once. `additional_paths` holds
+/// the path the manager claimed for each rendition, `None` for a skipped one.
+#[allow(clippy::too_many_arguments)]
+async fn run_multi_output(
+    app: AppHandle,
+    tx: mpsc::Sender<ManagerMessage>,
+    task: ConversionTask,
+    output_path: String,
+    additional_paths: Vec<Option<String>>,
+    probe: Option<ProbeMetadata>,
+    expected_duration: f64,
+    stop: watch::Receiver<bool>,
+    processes: TaskProcesses,
+) -> Result<(), ConversionError> {
+    let id = task.id.clone();
+    let mut outputs = vec![(output_path.clone(), task.config.clone())];
+    for (spec, path) in task.config.additional_outputs.iter().zip(additional_paths) {
+        if let Some(path) = path {
+            outputs.push((path, output_variant_config(&task.config, spec)));
+        }
+    }
+
+    let partials: Vec<_> = outputs
+        .iter()
+        .map(|(path, _)| partial_output_path(path, &id))
+        .collect();
+    let encode_outputs: Vec<_> = partials
+        .iter()
+        .zip(&outputs)
+        .map(|(partial, (_, config))| (partial.to_string_lossy().to_string(), config.clone()))
+        .collect();
+
+    let args = build_multi_output_args(&task.file_path, &encode_outputs);
+    let result = run_ffmpeg_pass(
+        &app,
+        &tx,
+        &processes,
+        args,
+        expected_duration,
+        PassSpan::FULL,
+        &stop,
+    )
+    .await;
+
+    let mut finished = Ok(());
+    for (partial, (path, _)) in partials.iter().zip(&outputs) {
+        let done = finish_partial_output(partial, path, result.is_ok());
+        finished = finished.and(done);
+    }
+    result?;
+    finished?;
+
+    let stopped = *stop.borrow();
+    let mut completed = Vec::new();
+    for (path, config) in &outputs {
+        let verification = verify_finished_output(
+            &app,
+            &id,
+            path,
+            config,
+            probe.as_ref(),
+            if stopped { 0.0 } else { expected_duration },
+        )
+        .await;
+        check_verification(path, &verification)?;
+        completed.push(CompletedOutput {
+            output_path: path.clone(),
+            output_size: std::fs::metadata(path).ok().map(|m| m.len()),
+            verification: Some(verification),
+        });
+    }
+
+    let main = completed.remove(0);
+    let mut payload = completed_payload(id, output_path, &task.config, main.verification);
+    payload.additional_outputs = completed;
+    if stopped {
+        mark_stopped(&app, &mut payload).await;
+    }
+    let _ = app.emit("conversion-completed", payload);
+    Ok(())
+}
+
+/// Encodes `input_path` into `encode_path` with one or two ffmpeg passes.
+#[allow(clippy::too_many_arguments)]
+async fn run_encode(
+    app: &AppHandle,
+    tx: &mpsc::Sender<ManagerMessage>,
+    processes: &TaskProcesses,
+    input_path: &str,
+    encode_path: &str,
+    config: &ConversionConfig,
+    expected_duration: f64,
+    stop: &watch::Receiver<bool>,
+) -> Result<(), ConversionError> {
+    if !uses_two_pass(config) {
+        let args = build_ffmpeg_args(input_path, encode_path, config);
+        return run_ffmpeg_pass(
+            app,
+            tx,
+            processes,
+            args,
+            expected_duration,
+            PassSpan::FULL,
+            stop,
+        )
+        .await;
+    }
+
+    let passlog_dir = task_temp_dir("passlog", processes.id());
+    std::fs::create_dir_all(&passlog_dir)?;
+    let log_prefix = passlog_dir.join("ffmpeg2pass");
+    let [first_pass, second_pass] = build_two_pass_args(
+        input_path,
+        encode_path,
+        config,
+        &log_prefix.to_string_lossy(),
+    );
+
+    let result = async {
+        let first = PassSpan {
+            offset: 0.0,
+            share: 50.0,
+            remaining_media: expected_duration,
+        };
+        run_ffmpeg_pass(
+            app,
+            tx,
+            processes,
+            first_pass,
+            expected_duration,
+            first,
+            stop,
+        )
+        .await?;
+        if *stop.borrow() {
+            return Err(ConversionError::Worker(
+                "Stopped during the analysis pass; no output was written".to_string(),
+            ));
+        }
+
+        let second = PassSpan {
+            offset: 50.0,
+            share: 50.0,
+            remaining_media: 0.0,
+        };
+        run_ffmpeg_pass(
+            app,
+            tx,
+            processes,
+            second_pass,
+            expected_duration,
+            second,
+            stop,
+        )
+        .await
+    }
+    .await;
+
+    let _ = std::fs::remove_dir_all(&passlog_dir);
+    result
+}
+
+/// Starts a sidecar for the task and registers it with the manager, so cancel
+/// and pause reach it. Once the task is cancelled no further stage is started.
+pub(crate) fn spawn_sidecar(
+    app: &AppHandle,
+    processes: &TaskProcesses,
+    program: &str,
+    args: Vec<String>,
+) -> Result<(mpsc::Receiver<CommandEvent>, CommandChild), ConversionError> {
+    if processes.is_cancelled() {
+        return Err(ConversionError::Cancelled(processes.id().to_string()));
+    }
+
+    let (rx, child) = app
+        .shell()
+        .sidecar(program)
+        .map_err(|e| ConversionError::Shell(e.to_string()))?
+        .args(args)
+        .spawn()
+        .map_err(|e| ConversionError::Shell(e.to_string()))?;
+    processes.register(child.pid())?;
+    if let Err(e) = set_process_priority(child.pid(), processes.priority()) {
+        // The sidecar still runs at the inherited priority; not worth failing the task.
+        let _ = app.emit(
+            "conversion-log",
+            LogPayload {
+                id: processes.id().to_string(),
+                line: format!("[PRIORITY] {}", e),
+            },
+        );
+    }
+    Ok((rx, child))
+}
+
+/// Runs one ffmpeg invocation to completion, forwarding its log and progress.
+/// A non-zero exit is classified from the tail of ffmpeg's stderr. Once `stop`
+/// is raised ffmpeg is sent `q`, which ends the encode and finalizes the file.
+async fn run_ffmpeg_pass(
+    app: &AppHandle,
+    tx: &mpsc::Sender<ManagerMessage>,
+    processes: &TaskProcesses,
+    ffmpeg_args: Vec<String>,
+    expected_duration: f64,
+    span: PassSpan,
+    stop: &watch::Receiver<bool>,
+) -> Result<(), ConversionError> {
+    let id = processes.id();
+    // Machine-readable progress goes to stdout; stderr is kept for the log panel.
+    let mut args = vec!["-progress".to_string(), "pipe:1".to_string()];
+    args.extend(ffmpeg_args);
+
+    let (mut rx, child) = spawn_sidecar(app, processes, "ffmpeg", args)?;
+    let pid = child.pid();
+
+    let mut stop = stop.clone();
+    let stop_watcher = tauri::async_runtime::spawn(async move {
+        let mut child = child;
+        if stop.wait_for(|stopped| *stopped).await.is_ok() {
+            let _ = child.write(b"q");
+        }
+    });
+
+    if span.offset == 0.0 {
+        let _ = app.emit("conversion-started", StartedPayload { id: id.to_string() });
+
+        emit_progress(
+            app,
+            tx,
+            ProgressPayload {
+                id: id.to_string(),
+                progress: 0.0,
+                ..Default::default()
+            },
+        );
+    }
 
     let mut exit_code: Option<i32> = None;
+    let mut stderr_tail = StderrTail::default();
     let mut total_duration: Option<f64> = None;
-
-    let expected_duration = {
-        let start_t = task
-            .config
-            .start_time
-            .as_deref()
-            .and_then(parse_time)
-            .unwrap_or(0.0);
-        let probe = crate::conversion::probe::probe_media_file(&app, &task.file_path)
-            .await
-            .ok();
-        let full_duration = probe
-            .and_then(|p| p.duration)
-            .as_deref()
-            .and_then(parse_time)
-            .unwrap_or(0.0);
-        let end_t = task
-            .config
-            .end_time
-            .as_deref()
-            .and_then(parse_time)
-            .unwrap_or(full_duration);
-        (end_t - start_t).max(0.0)
-    };
+    let mut progress_parser = ProgressParser::default();
+    let started_at = std::time::Instant::now();
 
     while let Some(event) = rx.recv().await {
         match event {
@@ -93,45 +594,64 @@ pub async fn run_ffmpeg_worker(
                     if line.is_empty() {
                         continue;
                     }
+                    stderr_tail.push(line);
 
                     let _ = app.emit(
                         "conversion-log",
                         LogPayload {
-                            id: id.clone(),
+                            id: id.to_string(),
                             line: line.to_string(),
                         },
                     );
 
-                    if let Some(caps) = TIME_REGEX.captures(line) {
-                        if let Some(match_str) = caps.get(1) {
-                            if let Some(current_time) = parse_time(match_str.as_str()) {
-                                let duration = if expected_duration > 0.0 {
-                                    expected_duration
-                                } else if let Some(d) = total_duration {
-                                    d
-                                } else if let Some(caps) = DURATION_REGEX.captures(line) {
-                                    if let Some(m) = caps.get(1) {
-                                        total_duration = parse_time(m.as_str());
-                                        total_duration.unwrap_or(0.0)
-                                    } else {
-                                        0.0
-                                    }
-                                } else {
-                                    0.0
-                                };
-
-                                if duration > 0.0 {
-                                    let progress = (current_time / duration * 100.0).min(100.0);
-                                    let _ = app.emit(
-                                        "conversion-progress",
-                                        ProgressPayload {
-                                            id: id.clone(),
-                                            progress,
-                                        },
-                                    );
-                                }
-                            }
-                        }
+                    if total_duration.is_none() {
+                        total_duration = DURATION_REGEX
+                            .captures(line)
+                            .and_then(|caps| caps.get(1))
+                            .and_then(|m| parse_time(m.as_str()));
+                    }
+                }
+            }
+            CommandEvent::Stdout(line_bytes) => {
+                let raw_output = String::from_utf8_lossy(&line_bytes).to_string();
+
+                for line in raw_output.split(['\r', '\n']) {
+                    let Some(update) = progress_parser.push_line(line) else {
+                        continue;
+                    };
+
+                    let duration = if expected_duration > 0.0 {
+                        expected_duration
+                    } else {
+                        total_duration.unwrap_or(0.0)
+                    };
+                    let out_time = update.out_time.unwrap_or(0.0);
+
+                    if duration > 0.0 {
+                        let fraction = if update.finished {
+                            1.0
+                        } else {
+                            (out_time / duration).min(1.0)
+                        };
+                        emit_progress(
+                            app,
+                            tx,
+                            ProgressPayload {
+                                id: id.to_string(),
+                                progress: span.offset + fraction * span.share,
+                                fps: update.fps,
+                                speed: update.speed,
+                                bitrate: update.bitrate_kbps,
+                                total_size: update.total_size,
+                                out_time: update.out_time,
+                                eta: estimate_eta(
+                                    duration + span.remaining_media,
+                                    out_time,
+                                    update.speed,
+                                    started_at.elapsed().as_secs_f64(),
+                                ),
+                            },
+                        );
                     }
                 }
             }
@@ -141,18 +661,12 @@ pub async fn run_ffmpeg_worker(
             _ => {}
         }
     }
+    stop_watcher.abort();
+    processes.unregister(pid);
 
     if exit_code == Some(0) {
-        let _ = app.emit(
-            "conversion-completed",
-            CompletedPayload {
-                id: id.clone(),
-                output_path,
-            },
-        );
         Ok(())
     } else {
-        let err_msg = format!("Process terminated with code {:?}", exit_code);
-        Err(ConversionError::Worker(err_msg))
+        Err(classify_ffmpeg_failure(&stderr_tail, exit_code))
     }
 }
@@ -1,6 +1,8 @@
 mod capabilities;
 mod conversion;
 mod dialog;
+
+pub use conversion::cli;
 use std::time::Duration;
 use tauri::window::{Color, EffectState};
 use tauri::{Manager, WebviewUrl, WebviewWindowBuilder, WindowEvent};
@@ -132,12 +134,28 @@ pub fn run() {
         .plugin(StoreBuilder::new().build())
         .invoke_handler(tauri::generate_handler![
             conversion::commands::queue_conversion,
+            conversion::commands::queue_pipeline,
             conversion::commands::pause_conversion,
             conversion::commands::resume_conversion,
+            conversion::commands::pause_all_conversions,
+            conversion::commands::resume_all_conversions,
             conversion::commands::cancel_conversion,
+            conversion::commands::stop_conversion,
             conversion::commands::probe_media,
             conversion::commands::get_max_concurrency,
             conversion::commands::set_max_concurrency,
+            conversion::commands::get_retry_policy,
+            conversion::commands::set_retry_policy,
+            conversion::commands::get_scheduler_config,
+            conversion::commands::set_scheduler_config,
+            conversion::commands::get_queue_schedule,
+            conversion::commands::set_queue_schedule,
+            conversion::commands::get_interrupted_conversions,
+            conversion::commands::requeue_interrupted_conversions,
+            conversion::commands::get_queue_state,
+            conversion::commands::move_queued_conversion,
+            conversion::commands::set_conversion_priority,
+            conversion::commands::remove_queued_conversion,
             capabilities::get_available_encoders,
             dialog::open_native_file_dialog,
             dialog::ask_native_dialog,
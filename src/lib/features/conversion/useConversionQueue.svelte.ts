@@ -67,6 +67,13 @@ export function createConversionQueue(callbacks: ConversionCallbacks) {
 						return f;
 					})
 				);
+			},
+			(payload) => {
+				callbacks.onFilesUpdate((files) =>
+					files.map((f) =>
+						f.id === payload.id ? { ...f, status: FileStatus.QUEUED, progress: 0 } : f
+					)
+				);
 			}
 		);
 
@@ -5,16 +5,99 @@ import type { ConversionConfig } from '../types';
 export interface ProgressEvent {
 	id: string;
 	progress: number;
+	fps?: number;
+	speed?: number;
+	bitrate?: number;
+	total_size?: number;
+	out_time?: number;
+	eta?: number;
 }
 
 export interface CompletedEvent {
 	id: string;
 	outputPath: string;
+	output_size?: number;
+	target_size?: number;
+	skipped?: boolean;
+	verification?: OutputVerification;
+	encoder_fallback?: EncoderFallback;
+	/** Set when the task was stopped early; the output covers `encoded_duration` seconds. */
+	partial?: boolean;
+	encoded_duration?: number;
+	additional_outputs?: CompletedOutput[];
 }
 
+export interface CompletedOutput {
+	output_path: string;
+	output_size?: number;
+	verification?: OutputVerification;
+}
+
+export interface EncoderFallback {
+	from: string;
+	to: string;
+	reason: string;
+}
+
+export interface OutputVerification {
+	status: 'passed' | 'warning' | 'failed';
+	issues: string[];
+}
+
+export type ConversionErrorKind =
+	| 'shell'
+	| 'io'
+	| 'json'
+	| 'channel'
+	| 'probe'
+	| 'worker'
+	| 'invalid_input'
+	| 'task_not_found'
+	| 'output_exists'
+	| 'encoder_unavailable'
+	| 'disk_full'
+	| 'permission_denied'
+	| 'invalid_data'
+	| 'unsupported_codec'
+	| 'nvenc_session_limit'
+	| 'cancelled';
+
 export interface ErrorEvent {
 	id: string;
 	error: string;
+	kind: ConversionErrorKind;
+	excerpt?: string;
+}
+
+export interface RetryEvent {
+	id: string;
+	attempt: number;
+	max_attempts: number;
+	delay_ms: number;
+	error: string;
+	kind: ConversionErrorKind;
+}
+
+export interface ScheduleEvent {
+	enabled: boolean;
+	open: boolean;
+	start: string;
+	end: string;
+	/** Minutes until the window next opens or closes; null while disabled. */
+	next_change_minutes: number | null;
+	/** Queued tasks waiting for the window. */
+	waiting: number;
+	/** Running tasks suspended because the window closed. */
+	held: number;
+	reason: string | null;
+}
+
+export interface PipelineEvent {
+	job_id: string;
+	step_id: string;
+	status: TaskStatus;
+	completed_steps: number;
+	total_steps: number;
 }
 
 export interface LogEvent {
@@ -26,18 +109,97 @@ export interface StartedEvent {
 	id: string;
 }
 
+export interface ConversionTask {
+	id: string;
+	filePath: string;
+	outputName?: string | null;
+	config: ConversionConfig;
+	priority: number;
+	collisionPolicy?: CollisionPolicy;
+	retryPolicy?: RetryPolicy | null;
+	/** Failed runs so far, kept across restarts so retries stay bounded. */
+	attempts?: number;
+	/** Set when the task is one step of a pipeline job. */
+	job?: JobLink | null;
+}
+
+export interface JobLink {
+	jobId: string;
+	dependsOn: string[];
+	inputFrom?: string | null;
+	intermediate: boolean;
+	/** The job's input, which outputs are named after. */
+	sourcePath: string;
+}
+
+export type CollisionPolicy = 'overwrite' | 'skip' | 'autoIncrement' | 'fail';
+
+export interface RetryPolicy {
+	/** Includes the first run; 1 disables retries. */
+	maxAttempts: number;
+	backoffMs: number;
+	backoffMultiplier: number;
+	retryableKinds: ConversionErrorKind[];
+}
+
+export type QueuePosition = 'top' | 'bottom' | { index: number };
+
+export type SchedulerMode = 'fixed' | 'adaptive';
+
+export interface SchedulerConfig {
+	/** `fixed` caps running tasks at `maxConcurrency`; `adaptive` uses the budgets. */
+	mode: SchedulerMode;
+	/** A software 1080p H.264 encode costs one CPU unit. */
+	cpuBudget: number;
+	/** A hardware encoder session costs one GPU unit. */
+	gpuBudget: number;
+}
+
+export interface QueueSchedule {
+	enabled: boolean;
+	/** Local time, `HH:MM`. A start later than `end` spans midnight. */
+	start: string;
+	end: string;
+}
+
+export interface PipelineStep {
+	/** Also the task id, so per-step progress arrives on the usual events. */
+	id: string;
+	config: ConversionConfig;
+	outputName?: string;
+	/** Earlier steps that must complete first. */
+	dependsOn?: string[];
+	/** Earlier step whose output this step reads; omit to read the job's source. */
+	inputFrom?: string;
+	/** Delete this step's output once the steps reading it have finished. */
+	intermediate?: boolean;
+}
+
+export interface TaskCost {
+	cpu: number;
+	gpu: number;
+}
+
 export async function startConversion(
 	id: string,
 	filePath: string,
 	config: ConversionConfig,
-	outputName?: string
+	outputName?: string,
+	priority?: number,
+	collisionPolicy?: CollisionPolicy,
+	presetName?: string,
+	retryPolicy?: RetryPolicy
 ) {
 	try {
 		await invoke('queue_conversion', {
 			id,
 			filePath,
 			outputName,
-			config
+			config,
+			priority,
+			collisionPolicy,
+			presetName,
+			retryPolicy
 		});
 	} catch (error) {
 		console.error('Failed to queue conversion:', error);
@@ -45,6 +207,29 @@ export async function startConversion(
 	}
 }
 
+export async function startPipeline(
+	jobId: string,
+	filePath: string,
+	steps: PipelineStep[],
+	priority?: number,
+	collisionPolicy?: CollisionPolicy,
+	presetName?: string
+) {
+	try {
+		await invoke('queue_pipeline', {
+			jobId,
+			filePath,
+			steps,
+			priority,
+			collisionPolicy,
+			presetName
+		});
+	} catch (error) {
+		console.error('Failed to queue pipeline:', error);
+		throw error;
+	}
+}
+
 export async function pauseConversion(id: string) {
 	try {
 		await invoke('pause_conversion', { id });
@@ -63,6 +248,35 @@ export async function resumeConversion(id: string) {
 	}
 }
 
+/** Suspends every running task and holds the queue. */
+export async function pauseAllConversions() {
+	try {
+		await invoke('pause_all_conversions');
+	} catch (error) {
+		console.error('Failed to pause all conversions:', error);
+		throw error;
+	}
+}
+
+export async function resumeAllConversions() {
+	try {
+		await invoke('resume_all_conversions');
+	} catch (error) {
+		console.error('Failed to resume all conversions:', error);
+		throw error;
+	}
+}
+
+/** Ends the encode early and keeps a playable output of what was encoded so far. */
+export async function stopConversion(id: string) {
+	try {
+		await invoke('stop_conversion', { id });
+	} catch (error) {
+		console.error('Failed to stop conversion:', error);
+		throw error;
+	}
+}
+
 export async function cancelConversion(id: string) {
 	try {
 		await invoke('cancel_conversion', { id });
@@ -72,12 +286,129 @@ export async function cancelConversion(id: string) {
 	}
 }
 
+export type TaskStatus =
+	| 'queued'
+	| 'running'
+	| 'paused'
+	| 'completed'
+	| 'failed'
+	| 'cancelled'
+	| 'interrupted'
+	| 'skipped'
+	| 'retrying'
+	| 'stopped'
+	| 'blocked';
+
+export interface TaskState {
+	id: string;
+	filePath: string;
+	outputName?: string | null;
+	jobId?: string | null;
+	status: TaskStatus;
+	priority: number;
+	position?: number | null;
+	pid?: number | null;
+	startedAt?: number | null;
+	finishedAt?: number | null;
+	progress: number;
+	error?: string | null;
+}
+
+export interface QueueState {
+	maxConcurrency: number;
+	/** Set by `pauseAllConversions`; queued tasks wait until `resumeAllConversions`. */
+	paused: boolean;
+	scheduler: SchedulerConfig;
+	/** Summed cost of the running tasks. */
+	inUse: TaskCost;
+	schedule: QueueSchedule;
+	/** False while the schedule holds queued tasks outside its window. */
+	windowOpen: boolean;
+	tasks: TaskState[];
+}
+
+export async function moveQueuedConversion(id: string, position: QueuePosition) {
+	try {
+		await invoke('move_queued_conversion', { id, position });
+	} catch (error) {
+		console.error('Failed to move queued conversion:', error);
+		throw error;
+	}
+}
+
+export async function setConversionPriority(id: string, priority: number) {
+	try {
+		await invoke('set_conversion_priority', { id, priority });
+	} catch (error) {
+		console.error('Failed to set conversion priority:', error);
+		throw error;
+	}
+}
+
+export async function removeQueuedConversion(id: string) {
+	try {
+		await invoke('remove_queued_conversion', { id });
+	} catch (error) {
+		console.error('Failed to remove queued conversion:', error);
+		throw error;
+	}
+}
+
+export async function getQueueState(): Promise<QueueState> {
+	try {
+		return await invoke<QueueState>('get_queue_state');
+	} catch (error) {
+		console.error('Failed to load queue state:', error);
+		throw error;
+	}
+}
+
+export async function getQueueSchedule(): Promise<QueueSchedule> {
+	try {
+		return await invoke<QueueSchedule>('get_queue_schedule');
+	} catch (error) {
+		console.error('Failed to load queue schedule:', error);
+		throw error;
+	}
+}
+
+/** Saved with the queue, so it also applies to tasks restored on launch. */
+export async function setQueueSchedule(schedule: QueueSchedule) {
+	try {
+		await invoke('set_queue_schedule', { schedule });
+	} catch (error) {
+		console.error('Failed to update queue schedule:', error);
+		throw error;
+	}
+}
+
+export async function getInterruptedConversions(): Promise<ConversionTask[]> {
+	try {
+		return await invoke<ConversionTask[]>('get_interrupted_conversions');
+	} catch (error) {
+		console.error('Failed to load interrupted conversions:', error);
+		throw error;
+	}
+}
+
+export async function requeueInterruptedConversions(ids?: string[]) {
+	try {
+		await invoke('requeue_interrupted_conversions', { ids });
+	} catch (error) {
+		console.error('Failed to requeue interrupted conversions:', error);
+		throw error;
+	}
+}
+
 export async function setupConversionListeners(
 	onProgress: (payload: ProgressEvent) => void,
 	onCompleted: (payload: CompletedEvent) => void,
 	onError: (payload: ErrorEvent) => void,
 	onLog: (payload: LogEvent) => void,
-	onStarted: (payload: StartedEvent) => void
+	onStarted: (payload: StartedEvent) => void,
+	onRetry?: (payload: RetryEvent) => void,
+	onSchedule?: (payload: ScheduleEvent) => void,
+	onPipeline?: (payload: PipelineEvent) => void
 ): Promise<UnlistenFn> {
 	const unlistenStarted = await listen<StartedEvent>('conversion-started', (event) => {
 		onStarted(event.payload);
@@ -99,11 +430,26 @@ export async function setupConversionListeners(
 		onLog(event.payload);
 	});
 
+	const unlistenRetry = await listen<RetryEvent>('conversion-retry', (event) => {
+		onRetry?.(event.payload);
+	});
+
+	const unlistenSchedule = await listen<ScheduleEvent>('conversion-schedule', (event) => {
+		onSchedule?.(event.payload);
+	});
+
+	const unlistenPipeline = await listen<PipelineEvent>('conversion-pipeline', (event) => {
+		onPipeline?.(event.payload);
+	});
+
 	return () => {
 		unlistenStarted();
 		unlistenProgress();
 		unlistenCompleted();
 		unlistenError();
 		unlistenLog();
+		unlistenRetry();
+		unlistenSchedule();
+		unlistenPipeline();
 	};
 }
@@ -1,11 +1,14 @@
 import { invoke } from '@tauri-apps/api/core';
 import { Store } from '@tauri-apps/plugin-store';
+import type { RetryPolicy, SchedulerConfig } from './conversion';
 
 const SETTINGS_STORE_PATH = 'app-settings.dat';
 const MAX_CONCURRENCY_KEY = 'maxConcurrency';
 const AUTO_UPDATE_CHECK_KEY = 'autoUpdateCheck';
 const WINDOW_OPACITY_KEY = 'windowOpacity';
 const FONT_FAMILY_KEY = 'fontFamily';
+const RETRY_POLICY_KEY = 'retryPolicy';
+const SCHEDULER_KEY = 'scheduler';
 
 const DEFAULT_MAX_CONCURRENCY = 2;
 const DEFAULT_AUTO_UPDATE_CHECK = true;
@@ -56,6 +59,50 @@ export async function persistMaxConcurrency(value: number): Promise<void> {
 	await store.save();
 }
 
+export async function loadInitialRetryPolicy(): Promise<RetryPolicy> {
+	try {
+		const store = await getStore();
+		const stored = await store.get<RetryPolicy>(RETRY_POLICY_KEY);
+
+		if (stored && typeof stored === 'object') {
+			await invoke('set_retry_policy', { policy: stored });
+		}
+	} catch (error) {
+		console.error('Failed to hydrate stored retry policy', error);
+	}
+
+	return invoke<RetryPolicy>('get_retry_policy');
+}
+
+export async function persistRetryPolicy(policy: RetryPolicy): Promise<void> {
+	await invoke('set_retry_policy', { policy });
+	const store = await getStore();
+	await store.set(RETRY_POLICY_KEY, policy);
+	await store.save();
+}
+
+export async function loadInitialScheduler(): Promise<SchedulerConfig> {
+	try {
+		const store = await getStore();
+		const stored = await store.get<SchedulerConfig>(SCHEDULER_KEY);
+
+		if (stored && typeof stored === 'object') {
+			await invoke('set_scheduler_config', { scheduler: stored });
+		}
+	} catch (error) {
+		console.error('Failed to hydrate stored scheduler settings', error);
+	}
+
+	return invoke<SchedulerConfig>('get_scheduler_config');
+}
+
+export async function persistScheduler(scheduler: SchedulerConfig): Promise<void> {
+	await invoke('set_scheduler_config', { scheduler });
+	const store = await getStore();
+	await store.set(SCHEDULER_KEY, scheduler);
+	await store.save();
+}
+
 export async function loadAutoUpdateCheck(): Promise<boolean> {
 	try {
 		const store = await getStore();
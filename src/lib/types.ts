@@ -29,6 +29,11 @@ export interface SubtitleTrack {
 	label?: string;
 }
 
+export interface TimeSegment {
+	start: string;
+	end: string;
+}
+
 export interface CropSettings {
 	enabled: boolean;
 	x: number;
@@ -43,8 +48,9 @@ export interface CropSettings {
 export interface ConversionConfig {
 	container: string;
 	videoCodec: string;
-	videoBitrateMode: 'crf' | 'bitrate';
+	videoBitrateMode: 'crf' | 'bitrate' | 'target_size';
 	videoBitrate: string;
+	targetSizeMb?: number | null;
 	audioCodec: string;
 	audioBitrate: string;
 	audioChannels: string;
@@ -63,6 +69,7 @@ export interface ConversionConfig {
 	preset: string;
 	startTime?: string;
 	endTime?: string;
+	segments?: TimeSegment[];
 	metadata: MetadataConfig;
 	rotation: '0' | '90' | '180' | '270';
 	flipHorizontal: boolean;
@@ -76,6 +83,33 @@ export interface ConversionConfig {
 	gifColors?: number;
 	gifDither?: 'none' | 'bayer' | 'floyd_steinberg' | 'sierra2_4a';
 	gifLoop?: number;
+	twoPass?: boolean;
+	hwFallback?: boolean;
+	outputDirectory?: string | null;
+	/** Tokens: {name} {ext} {container} {codec} {height} {date} {preset} */
+	outputTemplate?: string | null;
+	/** Encoder and filter threads per ffmpeg process; unset lets ffmpeg decide. */
+	threads?: number | null;
+	processPriority?: ProcessPriority;
+	/** Extra renditions encoded from the same decode as the main output. */
+	additionalOutputs?: OutputSpec[];
+}
+
+export type ProcessPriority = 'low' | 'normal' | 'high';
+
+/** Unset fields keep the main output's settings. */
+export interface OutputSpec {
+	/** Defaults to the main output's name plus the resolution. */
+	outputName?: string | null;
+	container: string;
+	videoCodec: string;
+	resolution: string;
+	videoBitrateMode?: string | null;
+	videoBitrate?: string | null;
+	crf?: number | null;
+	preset?: string | null;
+	audioCodec?: string | null;
+	audioBitrate?: string | null;
 }
 
 export type MetadataMode = 'preserve' | 'clean' | 'replace';